@@ -1,11 +1,32 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 
-use crate::utils::instrument::get_instrument_name;
+use serde::{Deserialize, Serialize};
+
+use crate::melody::{extract_bass_line, extract_melody_line, MelodyStrategy};
+use crate::quantize::{quantize_notes, QuantizeGrid};
+use crate::utils::harmony::{chord_role, detect_chord_root};
+use crate::utils::instrument::{get_instrument_name, MAX_PLAYABLE_OCTAVE, MIN_PLAYABLE_OCTAVE};
 use crate::utils::mml::midi_to_note_name;
 
 // 상수
 pub const TPB: u32 = 384;
 pub const GRID_SIZE: u32 = 24;
+pub const MAX_VOICES: usize = 6;
+
+// 동시 발음 그룹 몇 개마다 배분 진행을 알릴지
+const PROGRESS_INTERVAL: usize = 256;
+
+// 오래 걸리는 단계의 진행 알림 (끝난 수, 전체 수). Err를 반환하면 그 오류로 단계를 중단
+pub type Progress<'a> = &'a (dyn Fn(usize, usize) -> Result<(), String> + Sync);
+
+// 알림 없이 끝까지 진행 (항상 Ok이므로 이것을 넘긴 호출은 중단되지 않음)
+pub fn no_progress(_current: usize, _total: usize) -> Result<(), String> {
+    Ok(())
+}
+
+// 셋잇단 길이 (1536 / n 틱)
+const TRIPLET_LENGTHS: &[(u32, &str)] = &[(512, "3"), (256, "6"), (128, "12"), (64, "24")];
 
 #[derive(Debug, Clone)]
 pub struct Note {
@@ -15,6 +36,9 @@ pub struct Note {
     pub duration: u32,
     pub velocity: u8,
     pub instrument: String,
+    pub track: usize,
+    pub channel: u8,
+    pub program: u8,
 }
 
 // 점음표 포함 정확한 길이 매핑
@@ -47,12 +71,72 @@ fn get_exact_lengths(compress_mode: bool) -> HashMap<u32, &'static str> {
         map.insert(36, "64.");
         map.insert(24, "64");
     }
+
+    // 셋잇단 길이 (두 모드 공통)
+    for &(ticks, length) in TRIPLET_LENGTHS {
+        map.insert(ticks, length);
+    }
     
     map
 }
 
-fn snap_to_grid(tick: u32) -> u32 {
-    ((tick as f32 / GRID_SIZE as f32).round() as u32) * GRID_SIZE
+fn is_triplet_length(ticks: u32) -> bool {
+    TRIPLET_LENGTHS.iter().any(|&(t, _)| t == ticks)
+}
+
+// 후보 길이 (긴 것부터). 2진 격자 길이는 기존 길이만, 셋잇단이 섞인 길이는 전체 사용
+fn candidate_lengths(ticks: u32, exact_lengths: &HashMap<u32, &str>) -> Vec<u32> {
+    let binary_only = ticks.is_multiple_of(GRID_SIZE);
+    let mut lengths: Vec<u32> = exact_lengths
+        .keys()
+        .copied()
+        .filter(|&t| !(binary_only && is_triplet_length(t)))
+        .collect();
+    lengths.sort_by(|a, b| b.cmp(a));
+    lengths
+}
+
+// 셋잇단이 섞인 길이를 정확히 나누는 최소 개수 조합 (동전 문제 DP)
+fn find_exact_combination(ticks: u32, exact_lengths: &HashMap<u32, &str>) -> Option<Vec<(String, u32)>> {
+    let lengths = candidate_lengths(ticks, exact_lengths);
+    let mut result = Vec::new();
+    let mut remaining = ticks;
+
+    // 긴 음은 온음표로 먼저 채워 DP 범위를 줄임
+    while remaining > 1536 * 2 {
+        result.push(("1".to_string(), 1536));
+        remaining -= 1536;
+    }
+
+    let n = remaining as usize;
+    let mut count = vec![u32::MAX; n + 1];
+    let mut pick = vec![0u32; n + 1];
+    count[0] = 0;
+
+    for t in 1..=n {
+        for &length in &lengths {
+            let length = length as usize;
+            if length <= t && count[t - length] != u32::MAX && count[t - length] + 1 < count[t] {
+                count[t] = count[t - length] + 1;
+                pick[t] = length as u32;
+            }
+        }
+    }
+
+    if count[n] == u32::MAX {
+        return None;
+    }
+
+    let mut pieces = Vec::new();
+    let mut t = n;
+    while t > 0 {
+        pieces.push(pick[t]);
+        t -= pick[t] as usize;
+    }
+    pieces.sort_by(|a, b| b.cmp(a));
+
+    result.extend(pieces.into_iter().map(|p| (exact_lengths[&p].to_string(), p)));
+    Some(result)
 }
 
 // 정확히 매칭되는 길이 찾기 (점음표 포함)
@@ -66,12 +150,20 @@ fn find_tie_combination(
     max_ties: Option<usize>,
     exact_lengths: &HashMap<u32, &str>,
 ) -> Vec<(String, u32)> {
+    // 셋잇단이 섞인 길이는 정확히 나누어 떨어지는 조합 우선
+    if !ticks.is_multiple_of(GRID_SIZE) {
+        if let Some(combination) = find_exact_combination(ticks, exact_lengths) {
+            if max_ties.is_none_or(|max| combination.len() <= max) {
+                return combination;
+            }
+        }
+    }
+
     let mut result = Vec::new();
     let mut remaining = ticks;
     let mut tie_count = 0;
 
-    let mut lengths: Vec<u32> = exact_lengths.keys().copied().collect();
-    lengths.sort_by(|a, b| b.cmp(a));
+    let lengths = candidate_lengths(ticks, exact_lengths);
 
     for length_ticks in lengths {
         if let Some(max) = max_ties {
@@ -106,10 +198,9 @@ fn find_tie_combination(
 
 // 안전한 근사치 찾기 (타이 없이)
 fn find_safe_approximation(ticks: u32, exact_lengths: &HashMap<u32, &str>) -> Vec<(String, u32)> {
-    let closest = exact_lengths
-        .keys()
-        .min_by_key(|&&x| ((x as i64) - (ticks as i64)).abs())
-        .copied()
+    let closest = candidate_lengths(ticks, exact_lengths)
+        .into_iter()
+        .min_by_key(|&x| ((x as i64) - (ticks as i64)).abs())
         .unwrap_or(96);
 
     if let Some(&length_str) = exact_lengths.get(&closest) {
@@ -150,6 +241,24 @@ fn find_best_length(ticks: u32, octave: i32, exact_lengths: &HashMap<u32, &str>,
 }
 
 pub fn extract_midi_notes(midi_data: &[u8], _min_duration: u32) -> Result<(Vec<Note>, u32), String> {
+    extract_midi_notes_with_grid(midi_data, QuantizeGrid::default())
+}
+
+pub fn extract_midi_notes_with_grid(midi_data: &[u8], grid: QuantizeGrid) -> Result<(Vec<Note>, u32), String> {
+    extract_midi_notes_with_progress(midi_data, grid, &no_progress)
+}
+
+pub fn extract_midi_notes_with_progress(
+    midi_data: &[u8],
+    grid: QuantizeGrid,
+    progress: Progress,
+) -> Result<(Vec<Note>, u32), String> {
+    let (notes, bpm) = extract_raw_midi_notes(midi_data, progress)?;
+    Ok((normalize_notes(notes, grid), bpm))
+}
+
+// 격자 맞춤/중복 제거 전의 음표 (틱만 TPB 기준으로 환산), 트랙 하나를 읽을 때마다 진행 알림
+pub fn extract_raw_midi_notes(midi_data: &[u8], progress: Progress) -> Result<(Vec<Note>, u32), String> {
     let smf = midly::Smf::parse(midi_data).map_err(|e| format!("MIDI 파싱 오류: {}", e))?;
 
     let tpb = match smf.header.timing {
@@ -170,7 +279,8 @@ pub fn extract_midi_notes(midi_data: &[u8], _min_duration: u32) -> Result<(Vec<N
 
     // 음표 추출
     let mut notes = Vec::new();
-    for track in &smf.tracks {
+    for (track_index, track) in smf.tracks.iter().enumerate() {
+        progress(track_index, smf.tracks.len())?;
         let mut channel_programs: HashMap<u8, u8> = HashMap::new();
         let mut active: HashMap<(u8, u8), (u32, u8, u8)> = HashMap::new();
         let mut tick = 0u32;
@@ -210,24 +320,19 @@ pub fn extract_midi_notes(midi_data: &[u8], _min_duration: u32) -> Result<(Vec<N
                                         duration
                                     };
 
-                                    let start_snapped = snap_to_grid(start_adjusted);
-                                    let end_snapped = snap_to_grid(start_adjusted + duration_adjusted);
-                                    let mut duration_snapped = end_snapped.saturating_sub(start_snapped);
-
-                                    if duration_snapped < 24 {
-                                        duration_snapped = 24;
-                                    }
-
                                     let program = channel_programs.get(&channel).copied().unwrap_or(0);
                                     let instrument = get_instrument_name(program);
 
                                     notes.push(Note {
                                         note: note_num,
-                                        start: start_snapped,
-                                        end: start_snapped + duration_snapped,
-                                        duration: duration_snapped,
+                                        start: start_adjusted,
+                                        end: start_adjusted + duration_adjusted,
+                                        duration: duration_adjusted,
                                         velocity,
                                         instrument,
+                                        track: track_index,
+                                        channel,
+                                        program,
                                     });
                                 }
                             }
@@ -251,24 +356,19 @@ pub fn extract_midi_notes(midi_data: &[u8], _min_duration: u32) -> Result<(Vec<N
                                         duration
                                     };
 
-                                    let start_snapped = snap_to_grid(start_adjusted);
-                                    let end_snapped = snap_to_grid(start_adjusted + duration_adjusted);
-                                    let mut duration_snapped = end_snapped.saturating_sub(start_snapped);
-
-                                    if duration_snapped < 24 {
-                                        duration_snapped = 24;
-                                    }
-
                                     let program = channel_programs.get(&channel).copied().unwrap_or(0);
                                     let instrument = get_instrument_name(program);
 
                                     notes.push(Note {
                                         note: note_num,
-                                        start: start_snapped,
-                                        end: start_snapped + duration_snapped,
-                                        duration: duration_snapped,
+                                        start: start_adjusted,
+                                        end: start_adjusted + duration_adjusted,
+                                        duration: duration_adjusted,
                                         velocity,
                                         instrument,
+                                        track: track_index,
+                                        channel,
+                                        program,
                                     });
                                 }
                             }
@@ -281,6 +381,13 @@ pub fn extract_midi_notes(midi_data: &[u8], _min_duration: u32) -> Result<(Vec<N
         }
     }
 
+    Ok((notes, bpm))
+}
+
+// 격자에 맞춘 뒤 정렬하고, 같은 시점의 같은 음은 벨로시티가 가장 큰 것만 남김
+pub fn normalize_notes(mut notes: Vec<Note>, grid: QuantizeGrid) -> Vec<Note> {
+    quantize_notes(&mut notes, grid);
+
     // 정렬 및 중복 제거
     notes.sort_by(|a, b| a.start.cmp(&b.start).then(b.note.cmp(&a.note)));
 
@@ -307,13 +414,208 @@ pub fn extract_midi_notes(midi_data: &[u8], _min_duration: u32) -> Result<(Vec<N
         i = j;
     }
 
-    Ok((deduplicated, bpm))
+    deduplicated
 }
 
-pub fn allocate_voices_smart(notes: Vec<Note>) -> Vec<Vec<Note>> {
-    let num_voices = 6;
-    let mut voices: Vec<Vec<Note>> = vec![Vec::new(); num_voices];
+// 연주 가능 음역을 벗어난 음 처리 방식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RangePolicy {
+    // 옥타브 단위로 접어서 음역 안으로 이동
+    #[default]
+    Fold,
+    // 음역 밖의 음을 버림
+    Drop,
+    // 그대로 두고 개수만 보고
+    Report,
+}
+
+// 음역 적용. octave_range가 없으면 게임 기본 범위 (최저 > 최고로 뒤바뀐 범위는 바로잡아 사용)
+// (처리된 음, 음역을 벗어났던 음 개수) 반환
+pub fn enforce_range(
+    notes: Vec<Note>,
+    policy: RangePolicy,
+    octave_range: Option<(i32, i32)>,
+) -> (Vec<Note>, usize) {
+    let (low, high) = octave_range.unwrap_or((MIN_PLAYABLE_OCTAVE, MAX_PLAYABLE_OCTAVE));
+    let (min_octave, max_octave) = (low.min(high), low.max(high));
+    let min_note = ((min_octave + 1) * 12).clamp(0, 127);
+    let max_note = ((max_octave + 2) * 12 - 1).clamp(0, 127);
+
+    let mut out_of_range = 0;
+    let mut result = Vec::with_capacity(notes.len());
+
+    for mut note in notes {
+        let pitch = note.note as i32;
+        if pitch >= min_note && pitch <= max_note {
+            result.push(note);
+            continue;
+        }
+
+        out_of_range += 1;
+        match policy {
+            RangePolicy::Fold => {
+                let mut folded = pitch;
+                while folded < min_note {
+                    folded += 12;
+                }
+                while folded > max_note {
+                    folded -= 12;
+                }
+                // 범위가 한 옥타브보다 좁으면 경계에 맞춤
+                note.note = folded.clamp(min_note, max_note) as u8;
+                result.push(note);
+            }
+            RangePolicy::Drop => {}
+            RangePolicy::Report => result.push(note),
+        }
+    }
+
+    (result, out_of_range)
+}
+
+// 파트 배분 전략
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocationStrategy {
+    // 비어 있는 첫 번째 파트에 배정 (기존 방식)
+    #[default]
+    Greedy,
+    // 직전 음과의 음정 거리 합이 최소가 되도록 배정
+    VoiceLeading,
+    // 화성 역할(루트, 3음, 7음 우선)로 구성음을 골라 voice_count개 파트로 축약
+    HarmonicReduction,
+}
+
+// 모든 파트가 발음 중일 때 새 음을 처리하는 방식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    // 새 음을 버림 (기존 방식)
+    #[default]
+    Drop,
+    // 가장 덜 중요한 발음 중인 음을 잘라내고 새 음을 배정
+    Truncate,
+}
+
+#[derive(Debug, Clone)]
+pub struct AllocationOptions {
+    pub strategy: AllocationStrategy,
+    pub overflow: OverflowPolicy,
+    pub voice_count: usize, // 1 ~ MAX_VOICES
+    pub melody: MelodyStrategy,
+    pub bass_part: bool, // true면 최저음 라인을 마지막 파트로 분리 (voice_count가 2 이상일 때만)
+}
+
+impl AllocationOptions {
+    // 파트가 하나뿐이면 멜로디를 남기기 위해 베이스 파트를 나누지 않음
+    pub fn has_bass_part(&self) -> bool {
+        self.bass_part && self.voice_count >= 2
+    }
+}
+
+impl Default for AllocationOptions {
+    fn default() -> Self {
+        Self {
+            strategy: AllocationStrategy::default(),
+            overflow: OverflowPolicy::default(),
+            voice_count: MAX_VOICES,
+            melody: MelodyStrategy::default(),
+            bass_part: false,
+        }
+    }
+}
+
+pub fn allocate_voices(notes: Vec<Note>, options: &AllocationOptions) -> Vec<Vec<Note>> {
+    allocate_voices_with_progress(notes, options, &no_progress).unwrap_or_default()
+}
+
+// 동시 발음 그룹을 일정 개수 배분할 때마다 진행 알림 (알림이 Err이면 중단)
+pub fn allocate_voices_with_progress(
+    notes: Vec<Note>,
+    options: &AllocationOptions,
+    progress: Progress,
+) -> Result<Vec<Vec<Note>>, String> {
+    let num_voices = options.voice_count.clamp(1, MAX_VOICES);
+
+    if !options.has_bass_part() {
+        return allocate_upper_voices(notes, num_voices, options, progress);
+    }
+
+    // 베이스 파트는 항상 마지막 파트
+    let (bass, rest) = extract_bass_line(notes);
+    let mut voices = allocate_upper_voices(rest, num_voices - 1, options, progress)?;
+    voices.push(bass);
+    Ok(voices)
+}
+
+fn allocate_upper_voices(
+    notes: Vec<Note>,
+    num_voices: usize,
+    options: &AllocationOptions,
+    progress: Progress,
+) -> Result<Vec<Vec<Note>>, String> {
+    // 근접 멜로디는 배분 중에 고르고, 나머지 전략은 멜로디 파트를 먼저 뽑음
+    if options.melody == MelodyStrategy::Proximity {
+        return allocate_parts(notes, num_voices, options, progress);
+    }
+
+    let (melody, rest) = extract_melody_line(notes, options.melody);
+    let mut voices = vec![melody];
+    if num_voices > 1 {
+        voices.extend(allocate_parts(rest, num_voices - 1, options, progress)?);
+    }
+    Ok(voices)
+}
+
+fn allocate_parts(
+    notes: Vec<Note>,
+    num_voices: usize,
+    options: &AllocationOptions,
+    progress: Progress,
+) -> Result<Vec<Vec<Note>>, String> {
+    match options.strategy {
+        AllocationStrategy::Greedy => allocate_greedy(notes, num_voices, options.overflow, progress),
+        AllocationStrategy::VoiceLeading => {
+            allocate_by_matching(notes, num_voices, options.overflow, prioritize_simultaneous, progress)
+        }
+        AllocationStrategy::HarmonicReduction => {
+            allocate_by_matching(notes, num_voices, options.overflow, prioritize_harmonic, progress)
+        }
+    }
+}
+
+// 발음 중인 음 하나를 start 시점에서 잘라 파트를 비움
+// 벨로시티가 낮고, 오래 지속됐고, 안쪽 성부인 음을 먼저 자름 (멜로디/베이스는 마지막)
+fn make_room(voices: &mut [Vec<Note>], candidates: std::ops::Range<usize>, start: u32) -> Option<usize> {
+    let sounding: Vec<usize> = candidates
+        .filter(|&i| {
+            voices[i]
+                .last()
+                .is_some_and(|n| n.start < start && n.end > start)
+        })
+        .collect();
+
+    let lowest = sounding
+        .iter()
+        .map(|&i| voices[i].last().unwrap().note)
+        .min()?;
+
+    let victim = sounding.into_iter().min_by_key(|&i| {
+        let last = voices[i].last().unwrap();
+        let is_outer = i == 0 || last.note == lowest;
+        (is_outer, last.velocity, last.start)
+    })?;
+
+    let last = voices[victim].last_mut().unwrap();
+    last.end = start;
+    last.duration = start - last.start;
+
+    Some(victim)
+}
 
+// 동시 발음 그룹을 시작 시간 순으로 정리
+fn group_by_start(notes: Vec<Note>) -> Vec<Vec<Note>> {
     let mut start_times: HashMap<u32, Vec<Note>> = HashMap::new();
     for note in notes {
         start_times.entry(note.start).or_insert_with(Vec::new).push(note);
@@ -322,107 +624,299 @@ pub fn allocate_voices_smart(notes: Vec<Note>) -> Vec<Vec<Note>> {
     let mut sorted_times: Vec<u32> = start_times.keys().copied().collect();
     sorted_times.sort();
 
+    sorted_times
+        .into_iter()
+        .map(|t| start_times.remove(&t).unwrap())
+        .collect()
+}
+
+// 멜로디 선택: 직전 멜로디에서 12반음 이내인 가장 높은 음, 없으면 최고음
+fn select_melody(simultaneous: &[Note], last_melody_note: Option<u8>) -> Note {
+    if let Some(last_note) = last_melody_note {
+        let close_notes: Vec<&Note> = simultaneous
+            .iter()
+            .filter(|n| (n.note as i32 - last_note as i32).abs() <= 12)
+            .collect();
+
+        if let Some(best) = close_notes.into_iter().max_by_key(|n| n.note) {
+            return best.clone();
+        }
+    }
+
+    simultaneous[0].clone()
+}
+
+// 동시 발음 우선순위: 멜로디 → 베이스 → 벨로시티 순
+fn prioritize_simultaneous(mut simultaneous: Vec<Note>, last_melody_note: Option<u8>) -> Vec<Note> {
+    if simultaneous.len() == 1 {
+        return simultaneous;
+    }
+
+    simultaneous.sort_by(|a, b| b.note.cmp(&a.note));
+
+    let melody = select_melody(&simultaneous, last_melody_note);
+    let bass = simultaneous.last().unwrap().clone();
+
+    let mut remaining: Vec<Note> = simultaneous
+        .iter()
+        .filter(|n| n.note != melody.note && n.note != bass.note)
+        .cloned()
+        .collect();
+    remaining.sort_by(|a, b| b.velocity.cmp(&a.velocity));
+
+    let mut priority_notes = vec![melody.clone()];
+    if bass.note != melody.note {
+        priority_notes.push(bass);
+    }
+    priority_notes.extend(remaining);
+
+    priority_notes
+}
+
+pub fn allocate_voices_smart(notes: Vec<Note>) -> Vec<Vec<Note>> {
+    allocate_greedy(notes, MAX_VOICES, OverflowPolicy::Drop, &no_progress).unwrap_or_default()
+}
+
+fn allocate_greedy(
+    notes: Vec<Note>,
+    num_voices: usize,
+    overflow: OverflowPolicy,
+    progress: Progress,
+) -> Result<Vec<Vec<Note>>, String> {
+    let mut voices: Vec<Vec<Note>> = vec![Vec::new(); num_voices];
+
     let mut last_melody_note: Option<u8> = None;
 
-    for start_tick in sorted_times {
-        let mut simultaneous = start_times.remove(&start_tick).unwrap();
+    let groups = group_by_start(notes);
+    let total = groups.len();
+    for (index, simultaneous) in groups.into_iter().enumerate() {
+        if index % PROGRESS_INTERVAL == 0 {
+            progress(index, total)?;
+        }
+        for note in prioritize_simultaneous(simultaneous, last_melody_note) {
+            let mut target = (0..num_voices).find(|&i| {
+                voices[i].is_empty() || voices[i].last().unwrap().end <= note.start
+            });
 
-        if simultaneous.len() == 1 {
-            let note = simultaneous.into_iter().next().unwrap();
-            let mut assigned = false;
+            if target.is_none() && overflow == OverflowPolicy::Truncate {
+                target = make_room(&mut voices, 0..num_voices, note.start);
+            }
 
-            for i in 0..num_voices {
-                if voices[i].is_empty() || voices[i].last().unwrap().end <= note.start {
-                    voices[i].push(note.clone());
-                    if i == 0 {
-                        last_melody_note = Some(note.note);
-                    }
-                    assigned = true;
-                    break;
+            // 배정할 파트가 없으면 드롭
+            if let Some(i) = target {
+                if i == 0 {
+                    last_melody_note = Some(note.note);
                 }
+                voices[i].push(note);
             }
+        }
+    }
+
+    Ok(voices)
+}
+
+// 이전 음이 없는 파트에 배정할 때의 비용 (한 옥타브)
+const EMPTY_VOICE_COST: u32 = 12;
 
-            if !assigned {
-                // 드롭
+// 음표(행)를 파트(열)에 하나씩 배정하는 최소 비용 매칭 (비트마스크 DP)
+// 행 수는 열 수 이하여야 하며, 각 행에 배정된 열 인덱스를 반환
+fn min_cost_assignment(cost: &[Vec<u32>]) -> Vec<usize> {
+    let rows = cost.len();
+    if rows == 0 {
+        return Vec::new();
+    }
+    let cols = cost[0].len();
+    let full = 1usize << cols;
+
+    // dp[mask]: mask에 포함된 열을 앞쪽 popcount(mask)개 행에 배정한 최소 비용
+    let mut dp = vec![u32::MAX; full];
+    let mut parent = vec![usize::MAX; full];
+    dp[0] = 0;
+
+    for mask in 0..full {
+        if dp[mask] == u32::MAX {
+            continue;
+        }
+        let row = mask.count_ones() as usize;
+        if row >= rows {
+            continue;
+        }
+        for (col, &c) in cost[row].iter().enumerate() {
+            if mask & (1 << col) != 0 {
+                continue;
             }
-        } else {
-            simultaneous.sort_by(|a, b| b.note.cmp(&a.note));
+            let next = mask | (1 << col);
+            let candidate = dp[mask] + c;
+            if candidate < dp[next] {
+                dp[next] = candidate;
+                parent[next] = col;
+            }
+        }
+    }
 
-            // 멜로디 선택
-            let melody = if let Some(last_note) = last_melody_note {
-                let candidates: Vec<(i32, Note)> = simultaneous
-                    .iter()
-                    .map(|n| {
-                        let distance = (n.note as i32 - last_note as i32).abs();
-                        (distance, n.clone())
-                    })
-                    .collect();
+    let best_mask = (0..full)
+        .filter(|m| m.count_ones() as usize == rows)
+        .min_by_key(|&m| dp[m])
+        .unwrap();
+
+    let mut assignment = vec![0; rows];
+    let mut mask = best_mask;
+    for row in (0..rows).rev() {
+        let col = parent[mask];
+        assignment[row] = col;
+        mask &= !(1 << col);
+    }
 
-                let close_notes: Vec<Note> = candidates
-                    .iter()
-                    .filter(|(d, _)| *d <= 12)
-                    .map(|(_, n)| n.clone())
-                    .collect();
-
-                if !close_notes.is_empty() {
-                    close_notes.into_iter().max_by_key(|n| n.note).unwrap()
-                } else {
-                    simultaneous[0].clone()
-                }
-            } else {
-                simultaneous[0].clone()
-            };
+    assignment
+}
+
+// 성부 진행 고려 배분
+pub fn allocate_voices_voice_leading(notes: Vec<Note>) -> Vec<Vec<Note>> {
+    allocate_by_matching(notes, MAX_VOICES, OverflowPolicy::Drop, prioritize_simultaneous, &no_progress)
+        .unwrap_or_default()
+}
 
-            let bass = simultaneous.last().unwrap().clone();
+// 화성 축약: 옥타브 중복을 제거하고 화성 역할 순으로 골라 num_voices개 파트로 배정
+pub fn reduce_to_parts(notes: Vec<Note>, num_voices: usize) -> Vec<Vec<Note>> {
+    allocate_by_matching(notes, num_voices.clamp(1, MAX_VOICES), OverflowPolicy::Drop, prioritize_harmonic, &no_progress)
+        .unwrap_or_default()
+}
 
-            let mut remaining: Vec<Note> = simultaneous
-                .iter()
-                .filter(|n| n.note != melody.note && n.note != bass.note)
-                .cloned()
-                .collect();
-            remaining.sort_by(|a, b| b.velocity.cmp(&a.velocity));
+// 동시 발음 우선순위 (화성 축약): 멜로디 → 베이스 → 루트 → 3음 → 7음 → 텐션 → 5음
+// 멜로디/베이스를 제외한 옥타브 중복은 제거
+fn prioritize_harmonic(mut simultaneous: Vec<Note>, last_melody_note: Option<u8>) -> Vec<Note> {
+    if simultaneous.len() == 1 {
+        return simultaneous;
+    }
 
-            let mut priority_notes = vec![melody.clone()];
-            if simultaneous.len() > 1 && bass.note != melody.note {
-                priority_notes.push(bass);
-            }
-            priority_notes.extend(remaining);
-
-            for note in priority_notes {
-                let mut assigned = false;
-                for i in 0..num_voices {
-                    if voices[i].is_empty() || voices[i].last().unwrap().end <= note.start {
-                        voices[i].push(note.clone());
-                        if i == 0 {
-                            last_melody_note = Some(note.note);
-                        }
-                        assigned = true;
-                        break;
-                    }
-                }
+    simultaneous.sort_by_key(|n| Reverse(n.note));
+
+    let melody = select_melody(&simultaneous, last_melody_note);
+    let bass = simultaneous.last().unwrap().clone();
+
+    let mut pitch_classes: Vec<u8> = simultaneous.iter().map(|n| n.note % 12).collect();
+    pitch_classes.sort();
+    pitch_classes.dedup();
+    let root = detect_chord_root(&pitch_classes, bass.note % 12).unwrap_or(bass.note % 12);
+
+    let mut used_classes = vec![melody.note % 12];
+    let mut priority_notes = vec![melody.clone()];
+    if bass.note != melody.note {
+        used_classes.push(bass.note % 12);
+        priority_notes.push(bass.clone());
+    }
+
+    let mut inner: Vec<Note> = simultaneous
+        .into_iter()
+        .filter(|n| n.note != melody.note && n.note != bass.note)
+        .collect();
+    inner.sort_by(|a, b| {
+        chord_role(root, a.note % 12)
+            .cmp(&chord_role(root, b.note % 12))
+            .then(b.velocity.cmp(&a.velocity))
+    });
+
+    for note in inner {
+        if !used_classes.contains(&(note.note % 12)) {
+            used_classes.push(note.note % 12);
+            priority_notes.push(note);
+        }
+    }
+
+    priority_notes
+}
+
+// 멜로디는 첫 파트에 고정하고, 나머지 동시 발음은
+// 각 파트의 직전 음과의 거리 합이 최소가 되도록 배정
+fn allocate_by_matching(
+    notes: Vec<Note>,
+    num_voices: usize,
+    overflow: OverflowPolicy,
+    prioritize: fn(Vec<Note>, Option<u8>) -> Vec<Note>,
+    progress: Progress,
+) -> Result<Vec<Vec<Note>>, String> {
+    let mut voices: Vec<Vec<Note>> = vec![Vec::new(); num_voices];
+
+    let mut last_melody_note: Option<u8> = None;
+
+    let groups = group_by_start(notes);
+    let total = groups.len();
+    for (index, simultaneous) in groups.into_iter().enumerate() {
+        if index % PROGRESS_INTERVAL == 0 {
+            progress(index, total)?;
+        }
+        let start = simultaneous[0].start;
+        let mut priority_notes = prioritize(simultaneous, last_melody_note);
+
+        let is_free = |voice: &Vec<Note>| voice.is_empty() || voice.last().unwrap().end <= start;
 
-                if !assigned {
-                    // 드롭
+        if is_free(&voices[0]) {
+            let melody = priority_notes.remove(0);
+            last_melody_note = Some(melody.note);
+            voices[0].push(melody);
+        }
+
+        let mut free_voices: Vec<usize> = (1..num_voices).filter(|&i| is_free(&voices[i])).collect();
+
+        if overflow == OverflowPolicy::Truncate {
+            while priority_notes.len() > free_voices.len() {
+                match make_room(&mut voices, 1..num_voices, start) {
+                    Some(i) => free_voices.push(i),
+                    None => break,
                 }
             }
         }
+
+        // 빈 파트 수를 넘는 음은 우선순위가 낮은 것부터 드롭
+        priority_notes.truncate(free_voices.len());
+
+        let cost: Vec<Vec<u32>> = priority_notes
+            .iter()
+            .map(|note| {
+                free_voices
+                    .iter()
+                    .map(|&i| match voices[i].last() {
+                        Some(prev) => (note.note as i32 - prev.note as i32).unsigned_abs(),
+                        None => EMPTY_VOICE_COST,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let assignment = min_cost_assignment(&cost);
+        for (note, col) in priority_notes.into_iter().zip(assignment) {
+            voices[free_voices[col]].push(note);
+        }
     }
 
-    voices
+    Ok(voices)
 }
 
 pub fn generate_mml_final(voice_notes: &[Note], bpm: u32, start_octave: i32, compress_mode: bool) -> String {
+    generate_mml_incremental(voice_notes, bpm, start_octave, compress_mode).0
+}
+
+// 한 번의 생성으로 MML과 함께 음표마다 그 음까지의 MML 끝 위치 (바이트)를 반환
+// &mml[..ends[i - 1]]은 앞의 i개 음을 그대로 재생하는 MML이지만, 기본 길이(L)를 음표 전체로 정하므로
+// 앞의 i개 음만으로 generate_mml_final을 부른 결과와는 L이 달라 더 길거나 짧을 수 있음
+pub fn generate_mml_incremental(
+    voice_notes: &[Note],
+    bpm: u32,
+    start_octave: i32,
+    compress_mode: bool,
+) -> (String, Vec<usize>) {
     if voice_notes.is_empty() {
-        return String::new();
+        return (String::new(), Vec::new());
     }
 
     let exact_lengths = get_exact_lengths(compress_mode);
-    let mut mml = Vec::new();
+    let mut mml = String::new();
+    let mut ends = Vec::with_capacity(voice_notes.len());
 
     // 헤더
-    mml.push(format!("T{}", bpm));
-    mml.push("V15".to_string());
-    mml.push(format!("O{}", start_octave));
+    mml.push_str(&format!("T{}", bpm));
+    mml.push_str("V15");
+    mml.push_str(&format!("O{}", start_octave));
 
     let mut current_octave = start_octave;
 
@@ -448,7 +942,7 @@ pub fn generate_mml_final(voice_notes: &[Note], bpm: u32, start_octave: i32, com
         }
     }
 
-    mml.push(format!("L{}", default_length));
+    mml.push_str(&format!("L{}", default_length));
 
     let mut current_tick = 0u32;
 
@@ -461,10 +955,9 @@ pub fn generate_mml_final(voice_notes: &[Note], bpm: u32, start_octave: i32, com
             let rest_lengths = find_best_length(gap, 4, &exact_lengths, compress_mode);
 
             for (rest_length, rest_ticks) in rest_lengths {
-                if rest_length == default_length {
-                    mml.push("R".to_string());
-                } else {
-                    mml.push(format!("R{}", rest_length));
+                mml.push('R');
+                if rest_length != default_length {
+                    mml.push_str(&rest_length);
                 }
                 current_tick += rest_ticks;
             }
@@ -474,7 +967,7 @@ pub fn generate_mml_final(voice_notes: &[Note], bpm: u32, start_octave: i32, com
         let (note_name, octave) = midi_to_note_name(note.note);
 
         if octave != current_octave {
-            mml.push(format!("O{}", octave));
+            mml.push_str(&format!("O{}", octave));
             current_octave = octave;
         }
 
@@ -483,25 +976,86 @@ pub fn generate_mml_final(voice_notes: &[Note], bpm: u32, start_octave: i32, com
 
         // 첫 음표
         let (first_length, first_ticks) = &lengths[0];
-        if first_length == &default_length {
-            mml.push(note_name.clone());
-        } else {
-            mml.push(format!("{}{}", note_name, first_length));
+        mml.push_str(&note_name);
+        if first_length != &default_length {
+            mml.push_str(first_length);
         }
         current_tick += first_ticks;
 
         // 타이로 연결
         for (length_str, length_ticks) in lengths.iter().skip(1) {
-            mml.push("&".to_string());
-            if length_str == &default_length {
-                mml.push(note_name.clone());
-            } else {
-                mml.push(format!("{}{}", note_name, length_str));
+            mml.push('&');
+            mml.push_str(&note_name);
+            if length_str != &default_length {
+                mml.push_str(length_str);
             }
             current_tick += length_ticks;
         }
+
+        ends.push(mml.len());
     }
 
-    mml.join("")
+    (mml, ends)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(pitch: u8, start: u32, end: u32) -> Note {
+        Note {
+            note: pitch,
+            start,
+            end,
+            duration: end - start,
+            velocity: 100,
+            instrument: "Piano".to_string(),
+            track: 0,
+            channel: 0,
+            program: 0,
+        }
+    }
+
+    fn pitches(voice: &[Note]) -> Vec<u8> {
+        voice.iter().map(|n| n.note).collect()
+    }
+
+    #[test]
+    fn solo_line_stays_in_melody_with_bass_part() {
+        let notes = vec![
+            note(72, 0, 384),
+            note(74, 384, 768),
+            note(76, 768, 1152),
+            note(79, 1152, 1536),
+            note(48, 1152, 1536),
+        ];
+        let options = AllocationOptions {
+            voice_count: 3,
+            bass_part: true,
+            ..AllocationOptions::default()
+        };
+
+        let voices = allocate_voices(notes, &options);
+        assert_eq!(pitches(&voices[0]), [72, 74, 76, 79]);
+        assert_eq!(pitches(voices.last().unwrap()), [48]);
+    }
+
+    #[test]
+    fn bass_under_sustained_melody_goes_to_bass_part() {
+        let notes = vec![
+            note(76, 0, 1536),
+            note(48, 0, 384),
+            note(43, 384, 768),
+            note(45, 768, 1536),
+        ];
+        let options = AllocationOptions {
+            voice_count: 2,
+            bass_part: true,
+            ..AllocationOptions::default()
+        };
+
+        let voices = allocate_voices(notes, &options);
+        assert_eq!(pitches(&voices[0]), [76]);
+        assert_eq!(pitches(&voices[1]), [48, 43, 45]);
+    }
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use crate::converter::{Note, GRID_SIZE, TPB};
+use crate::meter::{measure_start, rebase_time_signatures, TimeSignature};
+
+// 발췌 구간 경계
+// 마디 번호는 1부터 시작하며, 시작 경계는 해당 마디의 처음, 끝 경계는 해당 마디의 끝(포함)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExcerptBound {
+    Measure(u32),
+    Seconds(f64),
+    Ticks(u32), // TPB(384) 기준
+}
+
+impl ExcerptBound {
+    // 경계를 TPB 기준 tick으로 변환 (격자에 맞춤)
+    fn to_tick(self, is_end: bool, bpm: u32, signatures: &[TimeSignature]) -> u32 {
+        let tick = match self {
+            ExcerptBound::Measure(measure) => {
+                let index = if is_end { measure } else { measure.saturating_sub(1) };
+                measure_start(signatures, index)
+            }
+            ExcerptBound::Seconds(seconds) => {
+                (seconds.max(0.0) * bpm as f64 / 60.0 * TPB as f64).round() as u32
+            }
+            ExcerptBound::Ticks(ticks) => ticks,
+        };
+        ((tick as f32 / GRID_SIZE as f32).round() as u32) * GRID_SIZE
+    }
+}
+
+// [start, end) 구간만 남기고, 경계에 걸친 음은 잘라낸 뒤 0부터 시작하도록 당김
+// 박자표도 같은 기준으로 당겨서 반환
+pub fn excerpt_notes(
+    notes: Vec<Note>,
+    start: Option<ExcerptBound>,
+    end: Option<ExcerptBound>,
+    bpm: u32,
+    signatures: &[TimeSignature],
+) -> Result<(Vec<Note>, Vec<TimeSignature>), String> {
+    if start.is_none() && end.is_none() {
+        return Ok((notes, signatures.to_vec()));
+    }
+
+    let start_tick = start.map_or(0, |b| b.to_tick(false, bpm, signatures));
+    let end_tick = end.map_or(u32::MAX, |b| b.to_tick(true, bpm, signatures));
+    if end_tick <= start_tick {
+        return Err("발췌 구간의 끝이 시작보다 앞섭니다".to_string());
+    }
+
+    let notes = notes
+        .into_iter()
+        .filter(|n| n.end > start_tick && n.start < end_tick)
+        .map(|mut n| {
+            n.start = n.start.max(start_tick) - start_tick;
+            n.end = n.end.min(end_tick) - start_tick;
+            n.duration = n.end - n.start;
+            n
+        })
+        .collect();
+
+    Ok((notes, rebase_time_signatures(signatures, start_tick)))
+}
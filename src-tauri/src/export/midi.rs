@@ -0,0 +1,93 @@
+use midly::num::{u15, u24, u28, u4, u7};
+use midly::{Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind};
+
+use super::ExportPart;
+use crate::converter::TPB;
+use crate::meter::TimeSignature;
+
+// 파트마다 이름 붙은 트랙 하나씩인 Format 1 MIDI (첫 트랙은 템포/박자표 전용)
+pub fn export_midi(parts: &[ExportPart], bpm: u32, signatures: &[TimeSignature]) -> Result<Vec<u8>, String> {
+    let mut smf = Smf::new(Header::new(Format::Parallel, Timing::Metrical(u15::new(TPB as u16))));
+
+    let tempo = 60_000_000 / bpm.max(1);
+    let mut conductor = vec![TrackEvent {
+        delta: u28::new(0),
+        kind: TrackEventKind::Meta(MetaMessage::Tempo(u24::new(tempo.min(0xFF_FFFF)))),
+    }];
+    let mut last_tick = 0;
+    for signature in signatures {
+        // 분모는 2의 거듭제곱으로 기록 (4 → 2)
+        let denominator = signature.denominator.max(1).ilog2() as u8;
+        conductor.push(TrackEvent {
+            delta: u28::new(signature.tick.saturating_sub(last_tick)),
+            kind: TrackEventKind::Meta(MetaMessage::TimeSignature(signature.numerator.min(255) as u8, denominator, 24, 8)),
+        });
+        last_tick = last_tick.max(signature.tick);
+    }
+    conductor.push(end_of_track(0));
+    smf.tracks.push(conductor);
+
+    for (index, part) in parts.iter().enumerate() {
+        // 10번 채널(9)은 타악기이므로 건너뜀
+        let channel = match index % 15 {
+            c if c >= 9 => c + 1,
+            c => c,
+        } as u8;
+        smf.tracks.push(part_track(part, channel));
+    }
+
+    let mut data = Vec::new();
+    smf.write_std(&mut data).map_err(|e| format!("MIDI 쓰기 오류: {}", e))?;
+    Ok(data)
+}
+
+fn end_of_track(delta: u32) -> TrackEvent<'static> {
+    TrackEvent {
+        delta: u28::new(delta),
+        kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+    }
+}
+
+fn part_track(part: &ExportPart, channel: u8) -> Vec<TrackEvent<'_>> {
+    let channel = u4::new(channel);
+
+    // (tick, 끔 여부, 음, 벨로시티). 같은 tick이면 끄기를 먼저
+    let mut messages: Vec<(u32, bool, u8, u8)> = Vec::new();
+    for note in &part.notes {
+        messages.push((note.start, false, note.note, note.velocity.max(1)));
+        messages.push((note.end, true, note.note, 0));
+    }
+    messages.sort_by_key(|&(tick, is_off, note, _)| (tick, !is_off, note));
+
+    let mut track = vec![
+        TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::TrackName(part.name.as_bytes())),
+        },
+        TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Midi {
+                channel,
+                message: MidiMessage::ProgramChange { program: u7::new(part.program.min(127)) },
+            },
+        },
+    ];
+
+    let mut last_tick = 0;
+    for (tick, is_off, note, velocity) in messages {
+        let key = u7::new(note.min(127));
+        let message = if is_off {
+            MidiMessage::NoteOff { key, vel: u7::new(0) }
+        } else {
+            MidiMessage::NoteOn { key, vel: u7::new(velocity.min(127)) }
+        };
+        track.push(TrackEvent {
+            delta: u28::new(tick - last_tick),
+            kind: TrackEventKind::Midi { channel, message },
+        });
+        last_tick = tick;
+    }
+
+    track.push(end_of_track(0));
+    track
+}
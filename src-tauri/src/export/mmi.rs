@@ -0,0 +1,70 @@
+use std::fmt::Write;
+
+use crate::meter::TimeSignature;
+use crate::utils::instrument::{get_instrument_name, get_mabiicco_program};
+
+// MabiIcco 트랙 하나 (멜로디/화음1/화음2 세 파트)
+#[derive(Debug, Clone)]
+pub struct MmiTrack {
+    pub name: String,
+    pub program: u8, // GM 프로그램 (MabiIcco 악기 번호로 바꿔서 기록)
+    pub parts: Vec<String>, // 최대 3개의 MML, 순서대로 멜로디, 화음1, 화음2
+}
+
+// (MML, GM 프로그램) 목록을 세 개씩 묶어 트랙으로 (트랙 이름은 첫 파트의 악기 이름)
+// by_instrument이면 프로그램이 바뀌는 곳에서도 새 트랙을 시작
+pub fn group_mmi_tracks(parts: &[(String, u8)], by_instrument: bool) -> Vec<MmiTrack> {
+    let mut tracks: Vec<MmiTrack> = Vec::new();
+    for (mml, program) in parts {
+        match tracks.last_mut() {
+            Some(track) if track.parts.len() < 3 && (!by_instrument || track.program == *program) => {
+                track.parts.push(mml.clone());
+            }
+            _ => tracks.push(MmiTrack {
+                name: get_instrument_name(*program),
+                program: *program,
+                parts: vec![mml.clone()],
+            }),
+        }
+    }
+    tracks
+}
+
+// MabiIcco 프로젝트 (.mmi) 파일 내용
+// 템포는 각 파트 MML 앞의 T 명령으로 들어가므로 따로 기록하지 않음
+pub fn export_mmi(tracks: &[MmiTrack], title: &str, signatures: &[TimeSignature]) -> Result<String, String> {
+    if tracks.is_empty() {
+        return Err("내보낼 파트가 없습니다".to_string());
+    }
+    if let Some(track) = tracks.iter().find(|t| t.parts.len() > 3) {
+        return Err(format!("트랙 '{}'의 파트가 3개를 넘습니다", track.name));
+    }
+
+    let time = signatures
+        .first()
+        .map_or((4, 4), |s| (s.numerator, s.denominator));
+
+    let mut mmi = String::new();
+    mmi.push_str("[mml-score]\n");
+    mmi.push_str("version=1\n");
+    let _ = writeln!(mmi, "title={}", single_line(title));
+    mmi.push_str("author=\n");
+    let _ = writeln!(mmi, "time={}/{}", time.0, time.1);
+
+    for track in tracks {
+        let mut parts = track.parts.clone();
+        parts.resize(3, String::new());
+        let _ = writeln!(mmi, "mml-track=MML@{};", parts.join(","));
+        let _ = writeln!(mmi, "name={}", single_line(&track.name));
+        let _ = writeln!(mmi, "program={}", get_mabiicco_program(track.program));
+        mmi.push_str("songProgram=-1\n");
+        mmi.push_str("panpot=64\n");
+    }
+
+    Ok(mmi)
+}
+
+// 한 줄 키=값 형식이므로 줄바꿈은 공백으로
+fn single_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
@@ -0,0 +1,14 @@
+// 배분된 파트를 다른 형식으로 내보내기
+pub mod midi;
+pub mod mmi;
+pub mod musicxml;
+
+use crate::converter::Note;
+
+// 내보낼 파트 하나 (크롭된 음표)
+#[derive(Debug, Clone)]
+pub struct ExportPart {
+    pub name: String,
+    pub program: u8,
+    pub notes: Vec<Note>,
+}
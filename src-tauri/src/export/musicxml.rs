@@ -0,0 +1,347 @@
+use std::cmp::Reverse;
+use std::fmt::Write;
+
+use super::ExportPart;
+use crate::converter::{Note, GRID_SIZE, TPB};
+use crate::meter::{measure_starts, TimeSignature};
+
+// 악보에 쓸 수 있는 길이 (ticks, 음표 종류, 점 개수, 셋잇단 여부). 긴 것부터
+const NOTE_VALUES: &[(u32, &str, u8, bool)] = &[
+    (1536, "whole", 0, false),
+    (1152, "half", 1, false),
+    (768, "half", 0, false),
+    (576, "quarter", 1, false),
+    (512, "half", 0, true),
+    (384, "quarter", 0, false),
+    (288, "eighth", 1, false),
+    (256, "quarter", 0, true),
+    (192, "eighth", 0, false),
+    (144, "16th", 1, false),
+    (128, "eighth", 0, true),
+    (96, "16th", 0, false),
+    (72, "32nd", 1, false),
+    (64, "16th", 0, true),
+    (48, "32nd", 0, false),
+    (24, "64th", 0, false),
+];
+
+const STEPS: [(&str, i32); 12] = [
+    ("C", 0), ("C", 1), ("D", 0), ("D", 1), ("E", 0), ("F", 0),
+    ("F", 1), ("G", 0), ("G", 1), ("A", 0), ("A", 1), ("B", 0),
+];
+
+// 파트마다 <part> 하나인 MusicXML (divisions = TPB이므로 duration은 tick 그대로)
+// 마디는 박자표 목록을 따르고, 마디선을 넘는 음은 타이로 나눔
+pub fn export_musicxml(parts: &[ExportPart], bpm: u32, signatures: &[TimeSignature]) -> Result<String, String> {
+    let total_end = parts
+        .iter()
+        .flat_map(|p| p.notes.iter())
+        .map(|n| n.end)
+        .max()
+        .unwrap_or(0);
+    if total_end == 0 {
+        return Err("내보낼 음표가 없습니다".to_string());
+    }
+
+    let bars = measure_starts(signatures, total_end);
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \"http://www.musicxml.org/dtds/partwise.dtd\">\n");
+    xml.push_str("<score-partwise version=\"4.0\">\n  <part-list>\n");
+    for (index, part) in parts.iter().enumerate() {
+        let id = index + 1;
+        let _ = write!(
+            xml,
+            "    <score-part id=\"P{id}\">\n      <part-name>{name}</part-name>\n      <score-instrument id=\"P{id}-I1\"><instrument-name>{name}</instrument-name></score-instrument>\n      <midi-instrument id=\"P{id}-I1\"><midi-channel>{channel}</midi-channel><midi-program>{program}</midi-program></midi-instrument>\n    </score-part>\n",
+            name = escape(&part.name),
+            channel = index % 16 + 1,
+            program = part.program.min(127) as u32 + 1,
+        );
+    }
+    xml.push_str("  </part-list>\n");
+
+    for (index, part) in parts.iter().enumerate() {
+        let _ = writeln!(xml, "  <part id=\"P{}\">", index + 1);
+        write_part(&mut xml, &part.notes, &bars, signatures, (index == 0).then_some(bpm));
+        xml.push_str("  </part>\n");
+    }
+
+    xml.push_str("</score-partwise>\n");
+    Ok(xml)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 쉼표(None)를 포함해 빈틈 없이 이어진 구간
+struct Segment {
+    start: u32,
+    end: u32,
+    pitch: Option<u8>,
+}
+
+// 파트를 단선율 구간 목록으로 (겹치는 음은 다음 음 시작에서 자름)
+fn part_segments(notes: &[Note]) -> Vec<Segment> {
+    let mut sorted: Vec<&Note> = notes.iter().collect();
+    sorted.sort_by_key(|n| n.start);
+
+    let mut segments = Vec::new();
+    let mut cursor = 0;
+    for (i, note) in sorted.iter().enumerate() {
+        let start = note.start.max(cursor);
+        let next_start = sorted.get(i + 1).map_or(u32::MAX, |n| n.start);
+        let end = note.end.min(next_start);
+        if end <= start {
+            continue;
+        }
+        if start > cursor {
+            segments.push(Segment { start: cursor, end: start, pitch: None });
+        }
+        segments.push(Segment { start, end, pitch: Some(note.note) });
+        cursor = end;
+    }
+    segments
+}
+
+type NoteValue = (u32, Option<&'static str>, u8, bool);
+
+// 길이를 악보에 쓸 수 있는 길이들로 나눔 (64분음표 격자면 셋잇단은 쓰지 않음)
+// 정확히 나누어떨어지는 최소 개수 조합을 쓰고, 그런 조합이 없을 때만 긴 것부터 채움
+// 음표 종류가 None인 조각은 어느 길이로도 채울 수 없는 자투리 (<type> 없이 <duration>만 기록)
+fn split_duration(ticks: u32) -> Vec<NoteValue> {
+    let binary_only = ticks.is_multiple_of(GRID_SIZE);
+    let values: Vec<(u32, &'static str, u8, bool)> = NOTE_VALUES
+        .iter()
+        .copied()
+        .filter(|&(_, _, _, triplet)| !(binary_only && triplet))
+        .collect();
+
+    exact_split(ticks, &values).unwrap_or_else(|| greedy_split(ticks, &values))
+}
+
+// 정확히 나누는 최소 개수 조합 (동전 문제 DP), 긴 것부터
+fn exact_split(ticks: u32, values: &[(u32, &'static str, u8, bool)]) -> Option<Vec<NoteValue>> {
+    let whole = values[0];
+    let mut pieces = Vec::new();
+    let mut remaining = ticks;
+
+    // 긴 음은 온음표로 먼저 채워 DP 범위를 줄임
+    while remaining > whole.0 * 2 {
+        pieces.push(whole);
+        remaining -= whole.0;
+    }
+
+    let n = remaining as usize;
+    let mut count = vec![u32::MAX; n + 1];
+    let mut pick = vec![0usize; n + 1];
+    count[0] = 0;
+
+    for t in 1..=n {
+        for (index, &(value, ..)) in values.iter().enumerate() {
+            let value = value as usize;
+            if value <= t && count[t - value] != u32::MAX && count[t - value] + 1 < count[t] {
+                count[t] = count[t - value] + 1;
+                pick[t] = index;
+            }
+        }
+    }
+
+    if count[n] == u32::MAX {
+        return None;
+    }
+
+    let mut t = n;
+    while t > 0 {
+        let value = values[pick[t]];
+        pieces.push(value);
+        t -= value.0 as usize;
+    }
+    pieces.sort_by_key(|p| Reverse(p.0));
+
+    Some(
+        pieces
+            .into_iter()
+            .map(|(value, kind, dots, triplet)| (value, Some(kind), dots, triplet))
+            .collect(),
+    )
+}
+
+// 긴 것부터 채우고 남는 자투리는 따로 타이로 이어 붙임
+fn greedy_split(ticks: u32, values: &[(u32, &'static str, u8, bool)]) -> Vec<NoteValue> {
+    let mut pieces = Vec::new();
+    let mut remaining = ticks;
+
+    while remaining > 0 {
+        match values.iter().find(|&&(value, ..)| value <= remaining) {
+            Some(&(value, kind, dots, triplet)) => {
+                pieces.push((value, Some(kind), dots, triplet));
+                remaining -= value;
+            }
+            None => {
+                pieces.push((remaining, None, 0, false));
+                remaining = 0;
+            }
+        }
+    }
+    pieces
+}
+
+fn write_part(xml: &mut String, notes: &[Note], bars: &[u32], signatures: &[TimeSignature], tempo: Option<u32>) {
+    let segments = part_segments(notes);
+    let average_pitch = notes.iter().map(|n| n.note as u32).sum::<u32>() / notes.len().max(1) as u32;
+    let mut current_signature: Option<(u32, u32)> = None;
+
+    for (number, window) in bars.windows(2).enumerate() {
+        let (measure_start, measure_end) = (window[0], window[1]);
+        let _ = writeln!(xml, "    <measure number=\"{}\">", number + 1);
+
+        let signature = signatures
+            .iter()
+            .rev()
+            .find(|s| s.tick <= measure_start)
+            .map_or((4, 4), |s| (s.numerator, s.denominator));
+        if number == 0 || current_signature != Some(signature) {
+            xml.push_str("      <attributes>\n");
+            if number == 0 {
+                let _ = writeln!(xml, "        <divisions>{}</divisions>", TPB);
+            }
+            let _ = writeln!(
+                xml,
+                "        <time><beats>{}</beats><beat-type>{}</beat-type></time>",
+                signature.0, signature.1
+            );
+            if number == 0 {
+                let clef = if average_pitch < 60 { ("F", 4) } else { ("G", 2) };
+                let _ = writeln!(xml, "        <clef><sign>{}</sign><line>{}</line></clef>", clef.0, clef.1);
+            }
+            xml.push_str("      </attributes>\n");
+            current_signature = Some(signature);
+        }
+
+        if let (0, Some(bpm)) = (number, tempo) {
+            let _ = writeln!(
+                xml,
+                "      <direction placement=\"above\"><direction-type><metronome><beat-unit>quarter</beat-unit><per-minute>{bpm}</per-minute></metronome></direction-type><sound tempo=\"{bpm}\"/></direction>"
+            );
+        }
+
+        let overlapping: Vec<&Segment> = segments
+            .iter()
+            .filter(|s| s.end > measure_start && s.start < measure_end)
+            .collect();
+
+        if overlapping.iter().all(|s| s.pitch.is_none()) {
+            let _ = writeln!(
+                xml,
+                "      <note><rest measure=\"yes\"/><duration>{}</duration></note>",
+                measure_end - measure_start
+            );
+            xml.push_str("    </measure>\n");
+            continue;
+        }
+
+        // 마디 안의 빈 곳 (파트가 먼저 끝난 경우)은 쉼표로 채움
+        let mut position = measure_start;
+        for segment in overlapping {
+            let start = segment.start.max(measure_start);
+            let end = segment.end.min(measure_end);
+            if start > position {
+                write_rest(xml, start - position);
+            }
+            write_segment(xml, segment, start, end);
+            position = end;
+        }
+        if measure_end > position {
+            write_rest(xml, measure_end - position);
+        }
+
+        xml.push_str("    </measure>\n");
+    }
+}
+
+fn write_rest(xml: &mut String, ticks: u32) {
+    for (duration, kind, dots, triplet) in split_duration(ticks) {
+        let _ = write!(xml, "      <note><rest/><duration>{}</duration>", duration);
+        write_note_type(xml, kind, dots, triplet);
+        xml.push_str("</note>\n");
+    }
+}
+
+fn write_segment(xml: &mut String, segment: &Segment, start: u32, end: u32) {
+    let Some(pitch) = segment.pitch else {
+        write_rest(xml, end - start);
+        return;
+    };
+    let (step, alter) = STEPS[(pitch % 12) as usize];
+    let octave = pitch as i32 / 12 - 1;
+
+    let mut position = start;
+    for (duration, kind, dots, triplet) in split_duration(end - start) {
+        let tie_stop = position > segment.start;
+        let tie_start = position + duration < segment.end;
+
+        xml.push_str("      <note><pitch>");
+        let _ = write!(xml, "<step>{}</step>", step);
+        if alter != 0 {
+            let _ = write!(xml, "<alter>{}</alter>", alter);
+        }
+        let _ = write!(xml, "<octave>{}</octave></pitch><duration>{}</duration>", octave, duration);
+        if tie_stop {
+            xml.push_str("<tie type=\"stop\"/>");
+        }
+        if tie_start {
+            xml.push_str("<tie type=\"start\"/>");
+        }
+        write_note_type(xml, kind, dots, triplet);
+        if tie_stop || tie_start {
+            xml.push_str("<notations>");
+            if tie_stop {
+                xml.push_str("<tied type=\"stop\"/>");
+            }
+            if tie_start {
+                xml.push_str("<tied type=\"start\"/>");
+            }
+            xml.push_str("</notations>");
+        }
+        xml.push_str("</note>\n");
+        position += duration;
+    }
+}
+
+fn write_note_type(xml: &mut String, kind: Option<&str>, dots: u8, triplet: bool) {
+    let Some(kind) = kind else {
+        return;
+    };
+    let _ = write!(xml, "<type>{}</type>", kind);
+    for _ in 0..dots {
+        xml.push_str("<dot/>");
+    }
+    if triplet {
+        xml.push_str("<time-modification><actual-notes>3</actual-notes><normal-notes>2</normal-notes></time-modification>");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_mixed_triplet_lengths_exactly() {
+        assert_eq!(
+            split_duration(320),
+            [(256, Some("quarter"), 0, true), (64, Some("16th"), 0, true)]
+        );
+        assert_eq!(split_duration(1152 + 24), [(1152, Some("half"), 1, false), (24, Some("64th"), 0, false)]);
+    }
+
+    #[test]
+    fn leaves_untyped_remainder_only_when_no_exact_split() {
+        assert_eq!(split_duration(40), [(24, Some("64th"), 0, false), (16, None, 0, false)]);
+        assert!(split_duration(1536 * 5 + 64).iter().all(|&(_, kind, ..)| kind.is_some()));
+    }
+}
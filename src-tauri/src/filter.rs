@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::converter::Note;
+use crate::utils::instrument::get_instrument_name;
+
+// 트랙/채널/프로그램 포함·제외 필터
+// 포함 목록이 비어 있으면 전부 포함, 제외 목록은 포함 목록보다 우선
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoteFilter {
+    pub include_tracks: Vec<usize>,
+    pub exclude_tracks: Vec<usize>,
+    pub include_channels: Vec<u8>, // 0부터 시작하는 채널 번호
+    pub exclude_channels: Vec<u8>,
+    pub include_programs: Vec<u8>, // GM 프로그램 번호
+    pub exclude_programs: Vec<u8>,
+}
+
+fn passes<T: PartialEq>(value: T, include: &[T], exclude: &[T]) -> bool {
+    (include.is_empty() || include.contains(&value)) && !exclude.contains(&value)
+}
+
+impl NoteFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include_tracks.is_empty()
+            && self.exclude_tracks.is_empty()
+            && self.include_channels.is_empty()
+            && self.exclude_channels.is_empty()
+            && self.include_programs.is_empty()
+            && self.exclude_programs.is_empty()
+    }
+
+    pub fn matches(&self, note: &Note) -> bool {
+        passes(note.track, &self.include_tracks, &self.exclude_tracks)
+            && passes(note.channel, &self.include_channels, &self.exclude_channels)
+            && passes(note.program, &self.include_programs, &self.exclude_programs)
+    }
+}
+
+pub fn filter_notes(notes: Vec<Note>, filter: &NoteFilter) -> Vec<Note> {
+    if filter.is_empty() {
+        return notes;
+    }
+    notes.into_iter().filter(|n| filter.matches(n)).collect()
+}
+
+// 필터 선택용: (트랙, 채널, 프로그램) 조합별 음 수
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteSource {
+    pub track: usize,
+    pub channel: u8,
+    pub program: u8,
+    pub instrument: String,
+    pub note_count: usize,
+}
+
+pub fn list_note_sources(notes: &[Note]) -> Vec<NoteSource> {
+    let mut counts: BTreeMap<(usize, u8, u8), usize> = BTreeMap::new();
+    for note in notes {
+        *counts.entry((note.track, note.channel, note.program)).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|((track, channel, program), note_count)| NoteSource {
+            track,
+            channel,
+            program,
+            instrument: get_instrument_name(program),
+            note_count,
+        })
+        .collect()
+}
@@ -0,0 +1,752 @@
+use std::collections::HashMap;
+
+use super::ImportedScore;
+use crate::converter::{Note, TPB};
+use crate::meter::{normalize_time_signatures, TimeSignature};
+use crate::utils::instrument::get_instrument_name;
+
+// 온음표 길이 (ticks). ABC 길이는 모두 온음표에 대한 비율로 다룸
+const WHOLE_NOTE: f64 = (TPB * 4) as f64;
+
+// 음이름별 반음 (C D E F G A B)
+const LETTER_SEMITONES: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+// 조표에 #이 붙는 순서 (F C G D A E B), b은 역순
+const SHARP_ORDER: [usize; 7] = [3, 0, 4, 1, 5, 2, 6];
+
+// ABC 악보(첫 번째 곡)를 음표로 변환
+// 헤더 X, T, M, L, Q, K와 V(성부), 본문의 임시표·타이·부점 리듬(> <)·잇단음표·반복/다른 끝을 처리
+pub fn import_abc(text: &str) -> Result<ImportedScore, String> {
+    let mut parser = AbcParser::default();
+
+    for line in text.lines() {
+        // % 이후는 주석
+        let line = line.split('%').next().unwrap_or_default().trim_end();
+        if !parser.read_line(line)? {
+            break;
+        }
+    }
+
+    if !parser.in_body {
+        return Err("ABC 본문이 없습니다 (K: 헤더 필요)".to_string());
+    }
+
+    let mut notes = Vec::new();
+    for (index, voice) in parser.voices.iter().enumerate() {
+        notes.extend(render_voice(&voice.elements, index));
+    }
+
+    // 박자표는 헤더의 M만 사용
+    let time_signatures = parser
+        .meter
+        .map(|(numerator, denominator)| TimeSignature {
+            tick: 0,
+            numerator,
+            denominator,
+        })
+        .into_iter()
+        .collect();
+
+    Ok(ImportedScore {
+        notes,
+        bpm: parser.tempo.map(|t| t.round() as u32).unwrap_or(120),
+        time_signatures: normalize_time_signatures(time_signatures),
+    })
+}
+
+#[derive(Debug, Clone)]
+enum Element {
+    Note { pitches: Vec<u8>, length: f64, tie: bool },
+    Rest(f64),
+    Bar,
+    RepeatStart,
+    RepeatEnd,
+    SectionEnd, // || 또는 |]
+    Ending(u32),
+}
+
+impl Element {
+    fn length_mut(&mut self) -> Option<&mut f64> {
+        match self {
+            Element::Note { length, .. } | Element::Rest(length) => Some(length),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Voice {
+    id: String,
+    elements: Vec<Element>,
+    key: [i32; 7],
+    // 마디 안 임시표: (음이름, 옥타브) → 변화량
+    accidentals: HashMap<(usize, i32), i32>,
+    // (길이 배율, 남은 음 수)
+    tuplet: Option<(f64, u32)>,
+    // 부점 리듬으로 다음 음에 곱할 배율
+    broken: Option<f64>,
+}
+
+#[derive(Default)]
+struct AbcParser {
+    started: bool, // X: 또는 첫 헤더를 읽음
+    in_body: bool, // K: 이후
+    unit: Option<f64>,
+    meter: Option<(u32, u32)>,
+    tempo: Option<f64>,
+    key: [i32; 7],
+    voices: Vec<Voice>,
+    current: usize,
+}
+
+impl AbcParser {
+    // false를 반환하면 곡이 끝난 것
+    fn read_line(&mut self, line: &str) -> Result<bool, String> {
+        if line.trim().is_empty() {
+            // 본문 뒤의 빈 줄은 곡의 끝
+            return Ok(!self.in_body);
+        }
+
+        if let Some((field, value)) = field_line(line) {
+            if field == 'X' && self.started {
+                return Ok(!self.in_body);
+            }
+            self.started = true;
+            self.read_field(field, value)?;
+            return Ok(true);
+        }
+
+        if self.in_body {
+            self.read_body(line)?;
+        }
+        Ok(true)
+    }
+
+    fn unit_length(&self) -> f64 {
+        // L가 없으면 박자가 3/4 미만일 때 1/16, 아니면 1/8
+        self.unit.unwrap_or(match self.meter {
+            Some((num, den)) if (num as f64 / den as f64) < 0.75 => 1.0 / 16.0,
+            _ => 1.0 / 8.0,
+        })
+    }
+
+    fn read_field(&mut self, field: char, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        match field {
+            'L' => {
+                self.unit = Some(parse_fraction(value).ok_or_else(|| format!("잘못된 L: {}", value))?);
+            }
+            'M' => {
+                let meter = parse_meter(value);
+                if self.meter.is_none() || !self.in_body {
+                    self.meter = meter;
+                }
+            }
+            'Q' => {
+                if let Some(tempo) = parse_tempo(value, self.unit_length()) {
+                    self.tempo.get_or_insert(tempo);
+                }
+            }
+            'K' => {
+                let key = parse_key(value)?;
+                if self.in_body {
+                    let voice = self.voice_mut();
+                    voice.key = key;
+                    voice.accidentals.clear();
+                } else {
+                    self.key = key;
+                    for voice in &mut self.voices {
+                        voice.key = key;
+                    }
+                    // 헤더에서 선언한 성부가 있으면 본문은 첫 성부부터
+                    self.current = 0;
+                    self.in_body = true;
+                }
+            }
+            'V' => {
+                let id = value.split_whitespace().next().unwrap_or_default().to_string();
+                self.current = match self.voices.iter().position(|v| v.id == id) {
+                    Some(index) => index,
+                    None => {
+                        self.voices.push(Voice {
+                            id,
+                            key: self.key,
+                            ..Voice::default()
+                        });
+                        self.voices.len() - 1
+                    }
+                };
+            }
+            // 제목, 가사, 작곡가 등은 무시
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn voice_mut(&mut self) -> &mut Voice {
+        if self.voices.is_empty() {
+            self.voices.push(Voice {
+                key: self.key,
+                ..Voice::default()
+            });
+            self.current = 0;
+        }
+        &mut self.voices[self.current]
+    }
+
+    fn read_body(&mut self, line: &str) -> Result<(), String> {
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                // 인라인 필드 [K:G] 등
+                '[' if chars.get(i + 2) == Some(&':') && chars.get(i + 1).is_some_and(|c| c.is_ascii_alphabetic()) => {
+                    let end = find_from(&chars, i, ']').ok_or("닫히지 않은 인라인 필드")?;
+                    let value: String = chars[i + 3..end].iter().collect();
+                    self.read_field(chars[i + 1], &value)?;
+                    i = end + 1;
+                }
+                // 다른 끝 [1, [2
+                '[' if chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) => {
+                    let (number, next) = read_number(&chars, i + 1);
+                    self.voice_mut().elements.push(Element::Ending(number.unwrap_or(1)));
+                    i = skip_ending_list(&chars, next);
+                }
+                '[' if chars.get(i + 1) == Some(&'|') => {
+                    i = self.read_bar(&chars, i + 1);
+                }
+                '[' => {
+                    i = self.read_chord(&chars, i + 1)?;
+                }
+                '|' | ':' => {
+                    i = self.read_bar(&chars, i);
+                }
+                '(' if chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) => {
+                    i = self.read_tuplet(&chars, i + 1);
+                }
+                '>' | '<' => {
+                    i = self.read_broken(&chars, i);
+                }
+                '-' => {
+                    if let Some(Element::Note { tie, .. }) = self.voice_mut().elements.last_mut() {
+                        *tie = true;
+                    }
+                    i += 1;
+                }
+                // 꾸밈음, 코드 기호, 장식음 기호는 무시
+                '{' => i = find_from(&chars, i, '}').map_or(chars.len(), |e| e + 1),
+                '"' => i = find_from(&chars, i + 1, '"').map_or(chars.len(), |e| e + 1),
+                '!' | '+' => i = find_from(&chars, i + 1, c).map_or(chars.len(), |e| e + 1),
+                'z' | 'x' => {
+                    let (length, next) = read_length(&chars, i + 1);
+                    let length = self.apply_rhythm(length * self.unit_length());
+                    self.voice_mut().elements.push(Element::Rest(length));
+                    i = next;
+                }
+                'Z' | 'X' => {
+                    // 여러 마디 쉼표
+                    let (count, next) = read_number(&chars, i + 1);
+                    let (num, den) = self.meter.unwrap_or((4, 4));
+                    let length = count.unwrap_or(1) as f64 * num as f64 / den as f64;
+                    self.voice_mut().elements.push(Element::Rest(length));
+                    i = next;
+                }
+                '^' | '_' | '=' | 'A'..='G' | 'a'..='g' => {
+                    let (pitch, length, next) = self.read_note(&chars, i)?;
+                    let length = self.apply_rhythm(length);
+                    self.voice_mut().elements.push(Element::Note {
+                        pitches: vec![pitch],
+                        length,
+                        tie: false,
+                    });
+                    i = next;
+                }
+                _ => i += 1,
+            }
+        }
+
+        Ok(())
+    }
+
+    // 음 하나 (임시표, 음이름, 옥타브 기호, 길이). 반환: (MIDI 음, 길이, 다음 위치)
+    fn read_note(&mut self, chars: &[char], mut i: usize) -> Result<(u8, f64, usize), String> {
+        let mut accidental = None;
+        while let Some(&c) = chars.get(i) {
+            match c {
+                '^' => accidental = Some(accidental.unwrap_or(0).max(0) + 1),
+                '_' => accidental = Some(accidental.unwrap_or(0).min(0) - 1),
+                '=' => accidental = Some(0),
+                _ => break,
+            }
+            i += 1;
+        }
+
+        let letter = *chars.get(i).ok_or("임시표 뒤에 음이 없습니다")?;
+        let (index, mut octave) = match letter {
+            'C'..='G' => (letter as usize - 'C' as usize, 4),
+            'A' | 'B' => (letter as usize - 'A' as usize + 5, 4),
+            'c'..='g' => (letter as usize - 'c' as usize, 5),
+            'a' | 'b' => (letter as usize - 'a' as usize + 5, 5),
+            _ => return Err(format!("잘못된 음이름: {}", letter)),
+        };
+        i += 1;
+
+        while let Some(&c) = chars.get(i) {
+            match c {
+                '\'' => octave += 1,
+                ',' => octave -= 1,
+                _ => break,
+            }
+            i += 1;
+        }
+
+        let voice = self.voice_mut();
+        let alter = match accidental {
+            Some(alter) => {
+                voice.accidentals.insert((index, octave), alter);
+                alter
+            }
+            None => voice
+                .accidentals
+                .get(&(index, octave))
+                .copied()
+                .unwrap_or(voice.key[index]),
+        };
+
+        let pitch = (octave + 1) * 12 + LETTER_SEMITONES[index] + alter;
+        if !(0..=127).contains(&pitch) {
+            return Err(format!("음역을 벗어난 음: {}", letter));
+        }
+
+        let (length, next) = read_length(chars, i);
+        Ok((pitch as u8, length * self.unit_length(), next))
+    }
+
+    // [CEG]2 화음. 길이는 첫 음의 길이에 닫는 괄호 뒤 배율을 곱함
+    fn read_chord(&mut self, chars: &[char], mut i: usize) -> Result<usize, String> {
+        let mut pitches = Vec::new();
+        let mut chord_length = None;
+        let mut tie = false;
+
+        while let Some(&c) = chars.get(i) {
+            match c {
+                ']' => break,
+                '^' | '_' | '=' | 'A'..='G' | 'a'..='g' => {
+                    let (pitch, length, next) = self.read_note(chars, i)?;
+                    pitches.push(pitch);
+                    chord_length.get_or_insert(length);
+                    i = next;
+                }
+                '-' => {
+                    tie = true;
+                    i += 1;
+                }
+                _ => i += 1,
+            }
+        }
+        if chars.get(i) != Some(&']') {
+            return Err("닫히지 않은 화음".to_string());
+        }
+
+        let (multiplier, mut next) = read_length(chars, i + 1);
+        if chars.get(next) == Some(&'-') {
+            tie = true;
+            next += 1;
+        }
+
+        if !pitches.is_empty() {
+            let length = self.apply_rhythm(chord_length.unwrap_or(self.unit_length()) * multiplier);
+            self.voice_mut().elements.push(Element::Note { pitches, length, tie });
+        }
+        Ok(next)
+    }
+
+    // 세로줄: | || |] [| |: :| :: 와 바로 뒤의 다른 끝 번호
+    fn read_bar(&mut self, chars: &[char], start: usize) -> usize {
+        let mut end = start;
+        while let Some(&c) = chars.get(end) {
+            if c == '|' || c == ':' || (c == ']' && end > start && chars[end - 1] == '|') {
+                end += 1;
+            } else {
+                break;
+            }
+        }
+        let token: String = chars[start..end].iter().collect();
+        let from_bracket = start > 0 && chars[start - 1] == '[';
+
+        let voice = self.voice_mut();
+        voice.accidentals.clear();
+
+        if token.starts_with(':') {
+            voice.elements.push(Element::RepeatEnd);
+        }
+        if token.ends_with(':') && token.len() > 1 {
+            voice.elements.push(Element::RepeatStart);
+        } else if !token.starts_with(':') {
+            if from_bracket || token.contains("||") || token.ends_with(']') {
+                voice.elements.push(Element::SectionEnd);
+            } else {
+                voice.elements.push(Element::Bar);
+            }
+        }
+
+        let mut next = end;
+        if let (Some(number), after) = read_number(chars, end) {
+            voice.elements.push(Element::Ending(number));
+            next = skip_ending_list(chars, after);
+        }
+        next
+    }
+
+    // (p:q:r — 다음 r개의 음을 q개 시간에 p개로 연주
+    fn read_tuplet(&mut self, chars: &[char], i: usize) -> usize {
+        let (p, mut next) = read_number(chars, i);
+        let p = p.unwrap_or(3);
+        let mut q = None;
+        let mut r = None;
+        if chars.get(next) == Some(&':') {
+            let (value, after) = read_number(chars, next + 1);
+            q = value;
+            next = after;
+            if chars.get(next) == Some(&':') {
+                let (value, after) = read_number(chars, next + 1);
+                r = value;
+                next = after;
+            }
+        }
+
+        let compound = self.meter.is_some_and(|(num, _)| num % 3 == 0 && num > 3);
+        let q = q.unwrap_or(match p {
+            3 | 6 => 2,
+            2 | 4 | 8 => 3,
+            _ if compound => 3,
+            _ => 2,
+        });
+
+        self.voice_mut().tuplet = Some((q as f64 / p as f64, r.unwrap_or(p)));
+        next
+    }
+
+    // a>b: 앞 음 ×1.5, 뒤 음 ×0.5 (>>는 1.75/0.25), <는 반대
+    fn read_broken(&mut self, chars: &[char], i: usize) -> usize {
+        let c = chars[i];
+        let mut count = 0;
+        while chars.get(i + count) == Some(&c) {
+            count += 1;
+        }
+        let shortened = 0.5f64.powi(count as i32);
+        let (previous, next) = if c == '>' {
+            (2.0 - shortened, shortened)
+        } else {
+            (shortened, 2.0 - shortened)
+        };
+
+        let voice = self.voice_mut();
+        if let Some(length) = voice.elements.iter_mut().rev().find_map(|e| e.length_mut()) {
+            *length *= previous;
+        }
+        voice.broken = Some(next);
+        i + count
+    }
+
+    // 잇단음표와 부점 리듬 배율 적용
+    fn apply_rhythm(&mut self, mut length: f64) -> f64 {
+        let voice = self.voice_mut();
+        if let Some(factor) = voice.broken.take() {
+            length *= factor;
+        }
+        if let Some((factor, remaining)) = voice.tuplet {
+            length *= factor;
+            voice.tuplet = (remaining > 1).then_some((factor, remaining - 1));
+        }
+        length
+    }
+}
+
+fn field_line(line: &str) -> Option<(char, &str)> {
+    let mut chars = line.chars();
+    let field = chars.next()?;
+    (field.is_ascii_alphabetic() && chars.next() == Some(':')).then(|| (field, &line[2..]))
+}
+
+fn find_from(chars: &[char], start: usize, target: char) -> Option<usize> {
+    chars[start.min(chars.len())..]
+        .iter()
+        .position(|&c| c == target)
+        .map(|p| p + start)
+}
+
+fn read_number(chars: &[char], mut i: usize) -> (Option<u32>, usize) {
+    let start = i;
+    while chars.get(i).is_some_and(|c| c.is_ascii_digit()) {
+        i += 1;
+    }
+    let number = chars.get(start..i).and_then(|digits| digits.iter().collect::<String>().parse().ok());
+    (number, i)
+}
+
+// [1,2 또는 [1-2 같은 목록은 첫 번호만 사용
+fn skip_ending_list(chars: &[char], mut i: usize) -> usize {
+    while chars.get(i).is_some_and(|&c| c == ',' || c == '-' || c.is_ascii_digit()) {
+        i += 1;
+    }
+    i
+}
+
+// 기본 길이에 대한 배율: "" 1, "2" 2, "/" 1/2, "//" 1/4, "3/2" 3/2, "/4" 1/4
+fn read_length(chars: &[char], i: usize) -> (f64, usize) {
+    let (numerator, mut i) = read_number(chars, i);
+    let mut length = numerator.unwrap_or(1).max(1) as f64;
+
+    while chars.get(i) == Some(&'/') {
+        let (denominator, next) = read_number(chars, i + 1);
+        length /= denominator.unwrap_or(2).max(1) as f64;
+        i = next;
+    }
+    (length, i)
+}
+
+fn parse_fraction(value: &str) -> Option<f64> {
+    let (num, den) = value.split_once('/')?;
+    let num: f64 = num.trim().parse().ok()?;
+    let den: f64 = den.trim().parse().ok()?;
+    (den > 0.0).then_some(num / den)
+}
+
+// M:3/4, M:C (4/4), M:C| (2/2), M:2+3/8
+fn parse_meter(value: &str) -> Option<(u32, u32)> {
+    match value {
+        "C" => Some((4, 4)),
+        "C|" => Some((2, 2)),
+        _ => {
+            let (num, den) = value.split_once('/')?;
+            let num = num.split('+').map(|n| n.trim().parse::<u32>().ok()).sum::<Option<u32>>()?;
+            let den = den.trim().parse().ok()?;
+            (num > 0 && den > 0).then_some((num, den))
+        }
+    }
+}
+
+// Q:1/4=120, Q:3/8=60, Q:"Allegro" 1/4=120, Q:120 (기본 길이 단위) → 4분음표 기준 BPM
+fn parse_tempo(value: &str, unit: f64) -> Option<f64> {
+    let mut text = String::new();
+    let mut in_quote = false;
+    for c in value.chars() {
+        if c == '"' {
+            in_quote = !in_quote;
+        } else if !in_quote {
+            text.push(c);
+        }
+    }
+
+    let (beat, per_minute) = match text.split_once('=') {
+        Some((beats, per_minute)) => {
+            let beat: f64 = beats
+                .split_whitespace()
+                .map(|b| if b == "C" { Some(unit) } else { parse_fraction(b) })
+                .sum::<Option<f64>>()?;
+            (beat, per_minute.trim().parse::<f64>().ok()?)
+        }
+        None => (unit, text.trim().parse::<f64>().ok()?),
+    };
+
+    (beat > 0.0 && per_minute > 0.0).then_some(per_minute * beat * 4.0)
+}
+
+// K:G, K:Dm, K:F#mix, K:Bb dor, K:D ^g 등 → 음이름별 변화량
+fn parse_key(value: &str) -> Result<[i32; 7], String> {
+    let mut key = [0i32; 7];
+    let mut tokens = value.split_whitespace().peekable();
+
+    let Some(tonic) = tokens.next() else {
+        return Ok(key);
+    };
+    if tonic.eq_ignore_ascii_case("none") || tonic.starts_with('H') {
+        return Ok(key);
+    }
+
+    let mut chars = tonic.chars();
+    let letter = chars.next().unwrap_or('C');
+    let mut fifths: i32 = match letter {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => -1,
+        'G' => 1,
+        'A' => 3,
+        'B' => 5,
+        // 조성 없이 임시표만 지정한 경우 (K:^f)
+        '^' | '_' | '=' => 0,
+        _ => return Err(format!("잘못된 조표: {}", value)),
+    };
+
+    let mut explicit: Vec<&str> = Vec::new();
+    let mut rest: String = if matches!(letter, '^' | '_' | '=') {
+        explicit.push(tonic);
+        String::new()
+    } else {
+        chars.as_str().to_string()
+    };
+    if rest.starts_with('#') {
+        fifths += 7;
+        rest.remove(0);
+    } else if rest.starts_with('b') {
+        fifths -= 7;
+        rest.remove(0);
+    }
+
+    // 선법은 음이름에 붙거나 다음 단어로 옴
+    if rest.is_empty() {
+        if let Some(next) = tokens.peek() {
+            if next.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) && !next.contains('=') {
+                rest = next.to_string();
+                tokens.next();
+            }
+        }
+    }
+    let mode = rest.to_ascii_lowercase();
+    fifths += match mode.get(..3).unwrap_or(&mode) {
+        "" | "maj" | "ion" => 0,
+        "m" | "min" | "aeo" => -3,
+        "mix" => -1,
+        "dor" => -2,
+        "phr" => -4,
+        "lyd" => 1,
+        "loc" => -5,
+        _ => 0,
+    };
+
+    for (i, &index) in SHARP_ORDER.iter().enumerate() {
+        if (i as i32) < fifths {
+            key[index] = 1;
+        }
+        if (i as i32) < -fifths {
+            key[SHARP_ORDER[6 - i]] = -1;
+        }
+    }
+
+    // 명시적 임시표 (^f _b =c)
+    explicit.extend(tokens.filter(|t| t.starts_with(['^', '_', '='])));
+    for token in explicit {
+        let alter = match token.chars().next() {
+            Some('^') => 1,
+            Some('_') => -1,
+            _ => 0,
+        };
+        if let Some(letter) = token.chars().last() {
+            let index = match letter.to_ascii_uppercase() {
+                'C' => 0,
+                'D' => 1,
+                'E' => 2,
+                'F' => 3,
+                'G' => 4,
+                'A' => 5,
+                'B' => 6,
+                _ => continue,
+            };
+            key[index] = alter;
+        }
+    }
+
+    Ok(key)
+}
+
+// 반복을 펼친 뒤 타이를 이어 음표로 변환
+fn render_voice(elements: &[Element], track: usize) -> Vec<Note> {
+    let mut notes: Vec<Note> = Vec::new();
+    let mut position = 0.0f64;
+    // 음높이 → 타이로 이어질 음의 인덱스
+    let mut open_ties: HashMap<u8, usize> = HashMap::new();
+
+    for element in expand_repeats(elements) {
+        match element {
+            Element::Note { pitches, length, tie } => {
+                let start = (position * WHOLE_NOTE).round() as u32;
+                position += length;
+                let end = (position * WHOLE_NOTE).round() as u32;
+
+                let mut still_open = HashMap::new();
+                for &pitch in pitches {
+                    let index = match open_ties.get(&pitch) {
+                        Some(&index) if notes[index].end == start => {
+                            notes[index].end = end;
+                            notes[index].duration = end - notes[index].start;
+                            index
+                        }
+                        _ => {
+                            notes.push(Note {
+                                note: pitch,
+                                start,
+                                end,
+                                duration: end - start,
+                                velocity: 100,
+                                instrument: get_instrument_name(0),
+                                track,
+                                channel: track.min(15) as u8,
+                                program: 0,
+                            });
+                            notes.len() - 1
+                        }
+                    };
+                    if *tie {
+                        still_open.insert(pitch, index);
+                    }
+                }
+                open_ties = still_open;
+            }
+            Element::Rest(length) => {
+                position += length;
+                open_ties.clear();
+            }
+            _ => {}
+        }
+    }
+
+    notes.retain(|n| n.duration > 0);
+    notes
+}
+
+// |: ... :| 는 두 번, [1 / [2 다른 끝은 해당 회차에만 연주
+fn expand_repeats(elements: &[Element]) -> Vec<&Element> {
+    let mut played = Vec::new();
+    let mut repeat_start = 0;
+    let mut pass = 1;
+    let mut ending: Option<u32> = None;
+    let mut i = 0;
+
+    while i < elements.len() {
+        let skipping = ending.is_some_and(|n| n != pass);
+        match &elements[i] {
+            Element::Ending(n) => ending = Some(*n),
+            Element::RepeatStart => {
+                repeat_start = i + 1;
+                pass = 1;
+                ending = None;
+            }
+            Element::RepeatEnd if !skipping => {
+                ending = None;
+                if pass == 1 {
+                    pass = 2;
+                    i = repeat_start;
+                    continue;
+                }
+                pass = 1;
+                repeat_start = i + 1;
+            }
+            Element::SectionEnd if ending.is_some() => {
+                ending = None;
+                if pass == 2 {
+                    pass = 1;
+                    repeat_start = i + 1;
+                }
+            }
+            Element::Note { .. } | Element::Rest(_) if !skipping => played.push(&elements[i]),
+            _ => {}
+        }
+        i += 1;
+    }
+
+    played
+}
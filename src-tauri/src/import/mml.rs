@@ -0,0 +1,311 @@
+use super::ImportedScore;
+use crate::converter::{Note, TPB};
+use crate::meter::TimeSignature;
+use crate::utils::instrument::get_instrument_name;
+
+// 온음표 길이 (ticks)
+const WHOLE_NOTE: f64 = (TPB * 4) as f64;
+
+// MML 텍스트를 음표로 변환 (MML에는 박자표가 없으므로 4/4로 취급)
+// "MML@a,b,c;" 묶음은 쉼표로 나눈 각 파트를, 그 외에는 전체를 한 파트로 읽음
+pub fn import_mml(text: &str) -> Result<ImportedScore, String> {
+    let parts = split_mml_parts(text);
+    if parts.is_empty() {
+        return Err("MML 내용이 없습니다".to_string());
+    }
+
+    let mut notes = Vec::new();
+    let mut bpm = None;
+    for (index, part) in parts.iter().enumerate() {
+        let timeline = parse_timeline(part).map_err(|e| format!("파트 {}: {}", index + 1, e))?;
+        if bpm.is_none() {
+            bpm = timeline.tempos.first().map(|&(_, tempo)| tempo);
+        }
+        notes.extend(timeline.notes.iter().map(|n| n.to_note(index)));
+    }
+
+    Ok(ImportedScore {
+        notes,
+        bpm: bpm.unwrap_or(120),
+        time_signatures: vec![TimeSignature::common_time(0)],
+    })
+}
+
+pub(crate) fn split_mml_parts(text: &str) -> Vec<String> {
+    let parts: Vec<String> = split_mml_blocks(text)
+        .into_iter()
+        .flat_map(|segment| match segment {
+            MmlSegment::Block { parts, .. } => parts,
+            MmlSegment::Text(_) => Vec::new(),
+        })
+        .collect();
+
+    if parts.is_empty() && !text.trim().is_empty() {
+        return vec![text.to_string()];
+    }
+    parts
+}
+
+// "MML@a,b,c;" 묶음과 묶음 밖의 글자를 순서대로 (다시 이어 붙이면 원래 문자열)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum MmlSegment {
+    Text(String),
+    Block { parts: Vec<String>, closed: bool }, // closed: 끝의 ";"가 있는지
+}
+
+pub(crate) fn split_mml_blocks(text: &str) -> Vec<MmlSegment> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+
+    while let Some(pos) = rest.find("MML@") {
+        if pos > 0 {
+            segments.push(MmlSegment::Text(rest[..pos].to_string()));
+        }
+        let body = &rest[pos + 4..];
+        let (end, closed) = body.find(';').map_or((body.len(), false), |end| (end, true));
+        segments.push(MmlSegment::Block {
+            parts: body[..end].split(',').map(|p| p.to_string()).collect(),
+            closed,
+        });
+        rest = &body[end + closed as usize..];
+    }
+
+    if !rest.is_empty() {
+        segments.push(MmlSegment::Text(rest.to_string()));
+    }
+    segments
+}
+
+// MML 파트 하나를 재생 순서대로 읽은 결과 (같은 음으로의 타이는 합쳐진 상태)
+// 두 파트의 타임라인이 같으면 게임에서 같은 소리가 남
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MmlTimeline {
+    pub notes: Vec<TimedNote>,
+    pub tempos: Vec<(u32, u32)>, // (tick, 템포), 바로 앞과 같은 템포는 제외
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TimedNote {
+    pub pitch: u8,
+    pub start: u32,
+    pub end: u32,
+    pub volume: Option<u32>, // V 명령 값 (None이면 지정 전)
+}
+
+impl TimedNote {
+    fn to_note(self, index: usize) -> Note {
+        Note {
+            note: self.pitch,
+            start: self.start,
+            end: self.end,
+            duration: self.end - self.start,
+            velocity: self.volume.map_or(127, |v| ((v * 127) as f64 / 15.0).round() as u8),
+            instrument: get_instrument_name(0),
+            track: index,
+            channel: index.min(15) as u8,
+            program: 0,
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return None;
+        }
+        self.chars[start..self.pos].iter().collect::<String>().parse().ok()
+    }
+
+    fn dots(&mut self) -> u32 {
+        let mut count = 0;
+        while self.peek() == Some('.') {
+            self.pos += 1;
+            count += 1;
+        }
+        count
+    }
+
+    // 길이 숫자와 점을 읽어 tick으로 변환 (숫자가 없으면 기본 길이)
+    fn length(&mut self, default_length: f64) -> Result<f64, String> {
+        let base = match self.number() {
+            Some(0) => return Err(format!("잘못된 길이 0 (위치 {})", self.pos)),
+            Some(n) => WHOLE_NOTE / n as f64,
+            None => default_length,
+        };
+        Ok(dotted(base, self.dots()))
+    }
+}
+
+pub(crate) fn dotted(base: f64, dots: u32) -> f64 {
+    let mut total = base;
+    let mut add = base;
+    for _ in 0..dots {
+        add /= 2.0;
+        total += add;
+    }
+    total
+}
+
+// 파트 하나를 읽어 음표와 템포 변경 목록으로
+pub(crate) fn parse_timeline(part: &str) -> Result<MmlTimeline, String> {
+    parse_commands(part).map(|(timeline, _)| timeline)
+}
+
+// parse_timeline과 같지만, 명령마다 (공백을 뺀 문자열에서의 글자 위치, 명령 직전 tick)도 함께 반환
+pub(crate) fn parse_commands(part: &str) -> Result<(MmlTimeline, Vec<(usize, u32)>), String> {
+    let mut parser = Parser {
+        chars: part.chars().filter(|c| !c.is_whitespace()).collect(),
+        pos: 0,
+    };
+
+    let mut notes: Vec<TimedNote> = Vec::new();
+    let mut tempos: Vec<(u32, u32)> = Vec::new();
+    let mut tick = 0.0f64;
+    let mut octave = 4i32;
+    let mut default_length = WHOLE_NOTE / 4.0;
+    let mut volume = None;
+    let mut tie = false;
+    let mut commands = Vec::new();
+
+    while let Some(c) = parser.peek() {
+        let position = parser.pos;
+        parser.pos += 1;
+        commands.push((position, tick.round() as u32));
+
+        let pitch = match c.to_ascii_uppercase() {
+            name @ 'A'..='G' => {
+                let base = match name {
+                    'C' => 0,
+                    'D' => 2,
+                    'E' => 4,
+                    'F' => 5,
+                    'G' => 7,
+                    'A' => 9,
+                    _ => 11,
+                };
+                let accidental = match parser.peek() {
+                    Some('+') | Some('#') => {
+                        parser.pos += 1;
+                        1
+                    }
+                    Some('-') => {
+                        parser.pos += 1;
+                        -1
+                    }
+                    _ => 0,
+                };
+                Some((octave + 1) * 12 + base + accidental)
+            }
+            'N' => {
+                let n = parser
+                    .number()
+                    .ok_or_else(|| format!("N 뒤에 음 번호가 없습니다 (위치 {})", position))?;
+                // N0 = O0C (MIDI 12)
+                Some(n as i32 + 12)
+            }
+            'R' => {
+                tick += parser.length(default_length)?;
+                tie = false;
+                None
+            }
+            'O' => {
+                octave = parser
+                    .number()
+                    .ok_or_else(|| format!("O 뒤에 옥타브가 없습니다 (위치 {})", position))?
+                    as i32;
+                None
+            }
+            '<' => {
+                octave -= 1;
+                None
+            }
+            '>' => {
+                octave += 1;
+                None
+            }
+            'L' => {
+                let n = parser
+                    .number()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| format!("잘못된 L 명령 (위치 {})", position))?;
+                default_length = dotted(WHOLE_NOTE / n as f64, parser.dots());
+                None
+            }
+            'T' => {
+                let t = parser
+                    .number()
+                    .ok_or_else(|| format!("T 뒤에 템포가 없습니다 (위치 {})", position))?;
+                let at = tick.round() as u32;
+                // 같은 위치의 템포는 마지막 것만, 바로 앞과 같은 템포는 무시
+                if tempos.last().is_some_and(|&(last, _)| last == at) {
+                    tempos.pop();
+                }
+                if tempos.last().is_none_or(|&(_, last)| last != t) {
+                    tempos.push((at, t));
+                }
+                None
+            }
+            'V' => {
+                let v = parser
+                    .number()
+                    .ok_or_else(|| format!("V 뒤에 음량이 없습니다 (위치 {})", position))?;
+                volume = Some(v.min(15));
+                None
+            }
+            '&' => {
+                tie = true;
+                None
+            }
+            other => return Err(format!("알 수 없는 명령 '{}' (위치 {})", other, position)),
+        };
+
+        let Some(pitch) = pitch else {
+            continue;
+        };
+        // N 명령에는 길이가 붙지 않음
+        let length = if c.eq_ignore_ascii_case(&'N') {
+            default_length
+        } else {
+            parser.length(default_length)?
+        };
+        if !(0..=127).contains(&pitch) {
+            return Err(format!("음역을 벗어난 음 (위치 {})", position));
+        }
+
+        let start = tick.round() as u32;
+        tick += length;
+        let end = tick.round() as u32;
+
+        // 같은 음으로의 타이는 앞 음을 연장
+        if tie {
+            if let Some(last) = notes.last_mut().filter(|n| n.pitch == pitch as u8 && n.end == start) {
+                last.end = end;
+                tie = false;
+                continue;
+            }
+        }
+        tie = false;
+
+        notes.push(TimedNote {
+            pitch: pitch as u8,
+            start,
+            end,
+            volume,
+        });
+    }
+
+    Ok((MmlTimeline { notes, tempos }, commands))
+}
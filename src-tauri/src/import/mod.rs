@@ -0,0 +1,15 @@
+// MIDI 외 입력 형식을 Note 목록으로 변환
+pub mod abc;
+pub mod mml;
+pub mod musicxml;
+
+use crate::converter::Note;
+use crate::meter::TimeSignature;
+
+// 가져온 악보 (격자 맞춤 전의 음표, 첫 템포, 박자표 목록)
+#[derive(Debug, Clone)]
+pub struct ImportedScore {
+    pub notes: Vec<Note>,
+    pub bpm: u32,
+    pub time_signatures: Vec<TimeSignature>,
+}
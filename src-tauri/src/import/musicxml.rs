@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+use roxmltree::{Document, Node, ParsingOptions};
+
+use super::ImportedScore;
+use crate::converter::{Note, TPB};
+use crate::meter::{normalize_time_signatures, TimeSignature};
+use crate::utils::instrument::{find_program_by_name, get_instrument_name};
+
+// <sound dynamics="100">이 벨로시티 90에 해당 (MusicXML 규격)
+const DEFAULT_VELOCITY: f64 = 90.0;
+
+// MusicXML(.musicxml) 또는 압축 MusicXML(.mxl)을 음표로 변환
+// 길이는 <duration>을 그대로 쓰므로 잇단음표는 이미 실제 길이로 반영됨
+pub fn import_musicxml(data: &[u8]) -> Result<ImportedScore, String> {
+    let xml = if data.starts_with(b"PK") {
+        read_mxl(data)?
+    } else {
+        String::from_utf8(data.to_vec()).map_err(|_| "MusicXML 인코딩 오류 (UTF-8이 아님)".to_string())?
+    };
+
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(&xml, options).map_err(|e| format!("MusicXML 파싱 오류: {}", e))?;
+    let root = doc.root_element();
+    if !root.has_tag_name("score-partwise") {
+        return Err(format!("지원하지 않는 MusicXML 형식: {}", root.tag_name().name()));
+    }
+
+    let part_programs = read_part_list(root);
+
+    let mut notes = Vec::new();
+    let mut tempo: Option<f64> = None;
+    let mut time_signatures = Vec::new();
+
+    for (index, part) in children(root, "part").enumerate() {
+        let id = part.attribute("id").unwrap_or_default();
+        let (program, channel) = part_programs.get(id).copied().unwrap_or((0, index.min(15) as u8));
+        let mut reader = PartReader {
+            track: index,
+            channel,
+            program,
+            instrument: get_instrument_name(program),
+            divisions: 1.0,
+            position: 0.0,
+            last_start: 0.0,
+            velocity: DEFAULT_VELOCITY,
+            transpose: 0,
+            open_ties: HashMap::new(),
+        };
+
+        for measure in children(part, "measure") {
+            reader.read_measure(measure, &mut notes, &mut tempo, &mut time_signatures);
+        }
+    }
+
+    Ok(ImportedScore {
+        notes,
+        bpm: tempo.map(|t| t.round() as u32).unwrap_or(120),
+        time_signatures: normalize_time_signatures(time_signatures),
+    })
+}
+
+// .mxl: META-INF/container.xml이 가리키는 파일, 없으면 첫 .xml/.musicxml 파일
+fn read_mxl(data: &[u8]) -> Result<String, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(|e| format!("MXL 압축 해제 오류: {}", e))?;
+
+    let read_entry = |archive: &mut zip::ZipArchive<Cursor<&[u8]>>, name: &str| -> Result<String, String> {
+        let mut file = archive.by_name(name).map_err(|e| format!("MXL 항목 오류 ({}): {}", name, e))?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|e| format!("MXL 항목 읽기 오류 ({}): {}", name, e))?;
+        Ok(content)
+    };
+
+    let rootfile = read_entry(&mut archive, "META-INF/container.xml").ok().and_then(|container| {
+        let doc = Document::parse(&container).ok()?;
+        let path = doc
+            .descendants()
+            .find(|n| n.has_tag_name("rootfile"))?
+            .attribute("full-path")?
+            .to_string();
+        Some(path)
+    });
+
+    let name = match rootfile {
+        Some(name) => name,
+        None => archive
+            .file_names()
+            .find(|n| !n.starts_with("META-INF/") && (n.ends_with(".xml") || n.ends_with(".musicxml")))
+            .map(|n| n.to_string())
+            .ok_or_else(|| "MXL 안에 MusicXML 파일이 없습니다".to_string())?,
+    };
+
+    read_entry(&mut archive, &name)
+}
+
+fn children<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |n| n.has_tag_name(name))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &'static str) -> Option<&'a str> {
+    child(node, name).and_then(|n| n.text()).map(|t| t.trim())
+}
+
+// 파트 id → (GM 프로그램, 채널)
+// <midi-instrument>가 있으면 그대로, 없으면 파트/악기 이름으로 추정
+fn read_part_list(root: Node) -> HashMap<String, (u8, u8)> {
+    let mut parts = HashMap::new();
+    let Some(part_list) = child(root, "part-list") else {
+        return parts;
+    };
+
+    for (index, score_part) in children(part_list, "score-part").enumerate() {
+        let Some(id) = score_part.attribute("id") else {
+            continue;
+        };
+        let midi = child(score_part, "midi-instrument");
+
+        let program = midi
+            .and_then(|m| child_text(m, "midi-program"))
+            .and_then(|p| p.parse::<u8>().ok())
+            .map(|p| p.saturating_sub(1).min(127))
+            .or_else(|| {
+                let instrument_name = child(score_part, "score-instrument")
+                    .and_then(|i| child_text(i, "instrument-name"));
+                let part_name = child_text(score_part, "part-name");
+                instrument_name
+                    .and_then(find_program_by_name)
+                    .or_else(|| part_name.and_then(find_program_by_name))
+            })
+            .unwrap_or(0);
+
+        let channel = midi
+            .and_then(|m| child_text(m, "midi-channel"))
+            .and_then(|c| c.parse::<u8>().ok())
+            .map(|c| c.saturating_sub(1).min(15))
+            .unwrap_or(index.min(15) as u8);
+
+        parts.insert(id.to_string(), (program, channel));
+    }
+
+    parts
+}
+
+// 셈여림 기호 → 벨로시티
+fn dynamics_velocity(mark: &str) -> Option<f64> {
+    let velocity = match mark {
+        "pppp" => 8.0,
+        "ppp" => 20.0,
+        "pp" => 36.0,
+        "p" => 52.0,
+        "mp" => 68.0,
+        "mf" => 84.0,
+        "f" | "sf" | "sfz" | "fz" => 100.0,
+        "ff" => 114.0,
+        "fff" | "ffff" => 127.0,
+        _ => return None,
+    };
+    Some(velocity)
+}
+
+struct PartReader {
+    track: usize,
+    channel: u8,
+    program: u8,
+    instrument: String,
+    divisions: f64,
+    position: f64,   // 현재 위치 (TPB 기준, 소수 포함)
+    last_start: f64, // <chord/> 음이 따를 직전 음의 시작
+    velocity: f64,
+    transpose: i32, // 기보음 → 실음 반음 수 (이조 악기)
+    // (음높이, 성부) → 타이가 이어질 음의 인덱스
+    open_ties: HashMap<(u8, String), usize>,
+}
+
+impl PartReader {
+    fn ticks(&self, node: Node) -> f64 {
+        child_text(node, "duration")
+            .and_then(|d| d.parse::<f64>().ok())
+            .map(|d| d * TPB as f64 / self.divisions)
+            .unwrap_or(0.0)
+    }
+
+    fn read_measure(
+        &mut self,
+        measure: Node,
+        notes: &mut Vec<Note>,
+        tempo: &mut Option<f64>,
+        time_signatures: &mut Vec<TimeSignature>,
+    ) {
+        let measure_start = self.position.round() as u32;
+        for element in measure.children().filter(|n| n.is_element()) {
+            match element.tag_name().name() {
+                "attributes" => {
+                    if let Some(divisions) = child_text(element, "divisions").and_then(|d| d.parse::<f64>().ok()) {
+                        if divisions > 0.0 {
+                            self.divisions = divisions;
+                        }
+                    }
+                    // 이조 악기는 기보음에 chromatic + octave-change 옥타브를 더한 음이 실제 소리
+                    if let Some(transpose) = child(element, "transpose") {
+                        let value = |name| child_text(transpose, name).and_then(|v| v.trim().parse::<i32>().ok()).unwrap_or(0);
+                        self.transpose = value("chromatic") + value("octave-change") * 12;
+                    }
+                    if let Some(time) = child(element, "time") {
+                        let beats = child_text(time, "beats").and_then(|b| b.parse().ok());
+                        let beat_type = child_text(time, "beat-type").and_then(|b| b.parse().ok());
+                        if let (Some(numerator), Some(denominator)) = (beats, beat_type) {
+                            time_signatures.push(TimeSignature {
+                                tick: measure_start,
+                                numerator,
+                                denominator,
+                            });
+                        }
+                    }
+                }
+                "direction" => self.read_direction(element, tempo),
+                "sound" => self.read_sound(element, tempo),
+                "backup" => self.position = (self.position - self.ticks(element)).max(0.0),
+                "forward" => self.position += self.ticks(element),
+                "note" => self.read_note(element, notes),
+                _ => {}
+            }
+        }
+    }
+
+    fn read_direction(&mut self, direction: Node, tempo: &mut Option<f64>) {
+        // <sound>가 메트로놈 표기보다 정확하므로 먼저 확인
+        if let Some(sound) = child(direction, "sound") {
+            self.read_sound(sound, tempo);
+        }
+
+        for direction_type in children(direction, "direction-type") {
+            if let Some(dynamics) = child(direction_type, "dynamics") {
+                if let Some(velocity) = dynamics
+                    .children()
+                    .filter(|n| n.is_element())
+                    .find_map(|n| dynamics_velocity(n.tag_name().name()))
+                {
+                    self.velocity = velocity;
+                }
+            }
+            if let Some(per_minute) = child(direction_type, "metronome")
+                .and_then(|m| child_text(m, "per-minute"))
+                .and_then(|p| p.parse::<f64>().ok())
+                .filter(|&p| p > 0.0)
+            {
+                tempo.get_or_insert(per_minute);
+            }
+        }
+    }
+
+    fn read_sound(&mut self, sound: Node, tempo: &mut Option<f64>) {
+        if let Some(value) = sound
+            .attribute("tempo")
+            .and_then(|t| t.parse::<f64>().ok())
+            .filter(|&t| t > 0.0)
+        {
+            tempo.get_or_insert(value);
+        }
+        if let Some(dynamics) = sound.attribute("dynamics").and_then(|d| d.parse::<f64>().ok()) {
+            self.velocity = dynamics * DEFAULT_VELOCITY / 100.0;
+        }
+    }
+
+    fn read_note(&mut self, element: Node, notes: &mut Vec<Note>) {
+        // 꾸밈음과 큐 음표는 길이가 없으므로 무시
+        if child(element, "grace").is_some() || child(element, "cue").is_some() {
+            return;
+        }
+
+        let duration = self.ticks(element);
+        let is_chord = child(element, "chord").is_some();
+        let start = if is_chord { self.last_start } else { self.position };
+        if !is_chord {
+            self.last_start = self.position;
+            self.position += duration;
+        }
+
+        let Some(pitch) = child(element, "pitch").and_then(|p| read_pitch(p, self.transpose)) else {
+            return;
+        };
+
+        let voice = child_text(element, "voice").unwrap_or("1").to_string();
+        let mut tie_start = false;
+        let mut tie_stop = false;
+        for tie in children(element, "tie") {
+            match tie.attribute("type") {
+                Some("start") => tie_start = true,
+                Some("stop") => tie_stop = true,
+                _ => {}
+            }
+        }
+
+        let start_tick = start.round() as u32;
+        let end_tick = (start + duration).round() as u32;
+        let key = (pitch, voice);
+
+        // 타이로 이어지는 음은 앞 음을 연장
+        if tie_stop {
+            if let Some(index) = self.open_ties.remove(&key) {
+                let tied = &mut notes[index];
+                tied.end = tied.end.max(end_tick);
+                tied.duration = tied.end - tied.start;
+                if tie_start {
+                    self.open_ties.insert(key, index);
+                }
+                return;
+            }
+        }
+
+        if end_tick <= start_tick {
+            return;
+        }
+
+        let velocity = element
+            .attribute("dynamics")
+            .and_then(|d| d.parse::<f64>().ok())
+            .map(|d| d * DEFAULT_VELOCITY / 100.0)
+            .unwrap_or(self.velocity);
+
+        notes.push(Note {
+            note: pitch,
+            start: start_tick,
+            end: end_tick,
+            duration: end_tick - start_tick,
+            velocity: velocity.round().clamp(1.0, 127.0) as u8,
+            instrument: self.instrument.clone(),
+            track: self.track,
+            channel: self.channel,
+            program: self.program,
+        });
+
+        if tie_start {
+            self.open_ties.insert(key, notes.len() - 1);
+        }
+    }
+}
+
+// 기보음에 transpose 반음을 더한 실음
+fn read_pitch(pitch: Node, transpose: i32) -> Option<u8> {
+    let step = match child_text(pitch, "step")? {
+        "C" => 0,
+        "D" => 2,
+        "E" => 4,
+        "F" => 5,
+        "G" => 7,
+        "A" => 9,
+        "B" => 11,
+        _ => return None,
+    };
+    // 미분음은 가장 가까운 반음으로
+    let alter = child_text(pitch, "alter")
+        .and_then(|a| a.parse::<f64>().ok())
+        .unwrap_or(0.0)
+        .round() as i32;
+    let octave: i32 = child_text(pitch, "octave")?.parse().ok()?;
+
+    let midi = (octave + 1) * 12 + step + alter + transpose;
+    (0..=127).contains(&midi).then_some(midi as u8)
+}
@@ -0,0 +1,147 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::meter::decode_denominator;
+
+// 변환 전 파일 정보 (tick은 파일 자체 해상도 기준)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MidiInfo {
+    pub format: u8, // 0: 단일 트랙, 1: 동시 재생 트랙, 2: 독립 트랙
+    pub resolution: u32, // 4분음표당 tick
+    pub tempo_map: Vec<TempoChange>,
+    pub time_signatures: Vec<TimeSignatureChange>,
+    pub tracks: Vec<TrackInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TempoChange {
+    pub tick: u32,
+    pub bpm: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeSignatureChange {
+    pub tick: u32,
+    pub numerator: u8,
+    pub denominator: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackInfo {
+    pub index: usize,
+    pub name: Option<String>,
+    pub channels: Vec<u8>,
+    pub programs: Vec<u8>,
+    pub note_count: usize,
+    pub lowest_note: Option<u8>,
+    pub highest_note: Option<u8>,
+    pub first_tick: Option<u32>, // 첫 음 시작
+    pub last_tick: Option<u32>,  // 마지막 음 끝
+}
+
+pub fn inspect_midi(midi_data: &[u8]) -> Result<MidiInfo, String> {
+    let smf = midly::Smf::parse(midi_data).map_err(|e| format!("MIDI 파싱 오류: {}", e))?;
+
+    let resolution = match smf.header.timing {
+        midly::Timing::Metrical(t) => t.as_int() as u32,
+        _ => return Err("SMPTE 타이밍 지원하지 않음".to_string()),
+    };
+    let format = match smf.header.format {
+        midly::Format::SingleTrack => 0,
+        midly::Format::Parallel => 1,
+        midly::Format::Sequential => 2,
+    };
+
+    let mut tempo_map = Vec::new();
+    let mut time_signatures = Vec::new();
+    let mut tracks = Vec::new();
+
+    for (index, track) in smf.tracks.iter().enumerate() {
+        let mut name = None;
+        let mut channels = BTreeSet::new();
+        let mut programs = BTreeSet::new();
+        let mut note_count = 0;
+        let mut lowest_note: Option<u8> = None;
+        let mut highest_note: Option<u8> = None;
+        let mut first_tick: Option<u32> = None;
+        let mut last_tick: Option<u32> = None;
+        let mut sounding: HashMap<(u8, u8), usize> = HashMap::new();
+        let mut tick = 0u32;
+
+        for event in track {
+            tick += event.delta.as_int();
+
+            match event.kind {
+                midly::TrackEventKind::Meta(midly::MetaMessage::TrackName(bytes)) if name.is_none() => {
+                    name = Some(String::from_utf8_lossy(bytes).trim().to_string());
+                }
+                midly::TrackEventKind::Meta(midly::MetaMessage::Tempo(tempo)) => {
+                    tempo_map.push(TempoChange {
+                        tick,
+                        bpm: 60_000_000.0 / tempo.as_int() as f64,
+                    });
+                }
+                midly::TrackEventKind::Meta(midly::MetaMessage::TimeSignature(numerator, denominator, _, _)) => {
+                    if let Some(denominator) = decode_denominator(denominator) {
+                        time_signatures.push(TimeSignatureChange {
+                            tick,
+                            numerator,
+                            denominator,
+                        });
+                    }
+                }
+                midly::TrackEventKind::Midi { channel, message } => {
+                    let ch = channel.as_int();
+                    match message {
+                        midly::MidiMessage::ProgramChange { program } => {
+                            programs.insert(program.as_int());
+                        }
+                        midly::MidiMessage::NoteOn { key, vel } if vel.as_int() > 0 => {
+                            let key = key.as_int();
+                            channels.insert(ch);
+                            note_count += 1;
+                            lowest_note = Some(lowest_note.map_or(key, |n| n.min(key)));
+                            highest_note = Some(highest_note.map_or(key, |n| n.max(key)));
+                            first_tick.get_or_insert(tick);
+                            *sounding.entry((ch, key)).or_default() += 1;
+                        }
+                        midly::MidiMessage::NoteOn { key, .. } | midly::MidiMessage::NoteOff { key, .. } => {
+                            if let Some(count) = sounding.get_mut(&(ch, key.as_int())) {
+                                if *count > 0 {
+                                    *count -= 1;
+                                    last_tick = Some(tick);
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        tracks.push(TrackInfo {
+            index,
+            name: name.filter(|n| !n.is_empty()),
+            channels: channels.into_iter().collect(),
+            programs: programs.into_iter().collect(),
+            note_count,
+            lowest_note,
+            highest_note,
+            first_tick,
+            last_tick,
+        });
+    }
+
+    tempo_map.sort_by_key(|t| t.tick);
+    time_signatures.sort_by_key(|t| t.tick);
+
+    Ok(MidiInfo {
+        format,
+        resolution,
+        tempo_map,
+        time_signatures,
+        tracks,
+    })
+}
@@ -1,8 +1,38 @@
 // Re-export modules for library usage
 pub mod utils;
 pub mod converter;
+pub mod excerpt;
+pub mod export;
+pub mod filter;
+pub mod import;
+pub mod inspect;
+pub mod melody;
+pub mod meter;
+pub mod minify;
+pub mod pretty;
+pub mod quantize;
+pub mod transpose;
 
 pub use converter::{
-    extract_midi_notes, allocate_voices_smart, generate_mml_final,
-    Note, TPB, GRID_SIZE,
-};
\ No newline at end of file
+    extract_midi_notes, extract_midi_notes_with_grid, extract_midi_notes_with_progress, extract_raw_midi_notes, allocate_voices_smart,
+    allocate_voices_voice_leading, allocate_voices, allocate_voices_with_progress, reduce_to_parts, generate_mml_final,
+    generate_mml_incremental, enforce_range, normalize_notes, no_progress, AllocationOptions, AllocationStrategy,
+    OverflowPolicy, Progress, RangePolicy, Note, TPB, GRID_SIZE, MAX_VOICES,
+};
+pub use excerpt::{excerpt_notes, ExcerptBound};
+pub use export::midi::export_midi;
+pub use export::mmi::{export_mmi, group_mmi_tracks, MmiTrack};
+pub use export::musicxml::export_musicxml;
+pub use export::ExportPart;
+pub use filter::{filter_notes, list_note_sources, NoteFilter, NoteSource};
+pub use import::abc::import_abc;
+pub use import::mml::import_mml;
+pub use import::musicxml::import_musicxml;
+pub use import::ImportedScore;
+pub use inspect::{inspect_midi, MidiInfo};
+pub use melody::{extract_bass_line, extract_melody_line, MelodyStrategy};
+pub use transpose::{auto_octave_shift, auto_transpose, transpose_notes, Transpose};
+pub use minify::{minify_mml, mml_equivalent};
+pub use pretty::{format_mml, strip_mml_formatting, MmlFormatOptions};
+pub use meter::{measure_starts, read_time_signatures, TimeSignature};
+pub use quantize::{collapse_strums, quantize_notes, QuantizeGrid};
\ No newline at end of file
@@ -1,21 +1,129 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
 
-mod utils;
-mod converter;
-
-use converter::{
-    extract_midi_notes, allocate_voices_smart, generate_mml_final,
-    Note, TPB,
+use mobinogi_mml_lib::{
+    extract_raw_midi_notes, normalize_notes, no_progress, allocate_voices_with_progress,
+    generate_mml_incremental, enforce_range,
+    auto_octave_shift, auto_transpose, collapse_strums, excerpt_notes, export_midi, export_mmi, export_musicxml, filter_notes,
+    group_mmi_tracks, import_abc, import_mml, import_musicxml, inspect_midi, list_note_sources, format_mml, minify_mml,
+    mml_equivalent, read_time_signatures, transpose_notes,
+    AllocationOptions, AllocationStrategy, ExcerptBound, ExportPart, ImportedScore, MelodyStrategy, MidiInfo, MmlFormatOptions,
+    Note, NoteFilter, NoteSource, OverflowPolicy, QuantizeGrid, RangePolicy, TimeSignature, Transpose,
+    GRID_SIZE, MAX_VOICES, TPB,
+};
+use mobinogi_mml_lib::utils::instrument::{
+    get_instrument_name, MAX_PLAYABLE_OCTAVE, MIN_PLAYABLE_OCTAVE,
 };
+use mobinogi_mml_lib::utils::mml::{check_mml_octaves, mml_char_count};
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ConversionOptions {
     mode: String, // "normal" or "instrument"
     char_limit: usize,
     compress_mode: bool, // true: 글자수 우선 (점음표/타이 최소화), false: 정확도 우선
+    #[serde(default)]
+    allocation: AllocationStrategy, // "greedy", "voice_leading", "harmonic_reduction"
+    #[serde(default)]
+    overflow: OverflowPolicy, // "drop" 또는 "truncate"
+    #[serde(default = "default_voice_count")]
+    voice_count: usize, // 파트 수 (1~6)
+    #[serde(default)]
+    melody: MelodyStrategy, // "proximity", "skyline", {"track": n}, {"channel": n}, "velocity", "longest_line"
+    #[serde(default)]
+    bass_part: bool, // 최저음 라인을 별도 베이스 파트로 분리 (파트 수가 1이면 무시)
+    #[serde(default)]
+    range_policy: RangePolicy, // "fold", "drop", "report"
+    #[serde(default)]
+    octave_range: Option<(i32, i32)>, // 지정 시 게임 기본 범위 대신 쓸 옥타브 범위 [최저, 최고]
+    #[serde(default)]
+    transpose: Transpose, // 곡 전체: {"semitones": n} 또는 "auto"
+    #[serde(default)]
+    part_transpose: Vec<Transpose>, // 결과 파트 순서별 조옮김 (빈 파트 제외, 베이스는 맨 뒤, "auto"는 옥타브 단위)
+    #[serde(default)]
+    grid: QuantizeGrid, // "sixteenth", "thirty_second", "sixty_fourth", "sixty_fourth_with_triplets", "triplet_eighth", "triplet_sixteenth", "auto"
+    #[serde(default)]
+    strum_tolerance: u32, // 스트럼을 한 화음으로 합칠 시작 시점 차이 (격자 맞춤 전 ticks, 4분음표 = 384, 0이면 사용 안 함)
+    #[serde(default)]
+    excerpt_start: Option<ExcerptBound>, // 발췌 시작: {"measure": n}, {"seconds": x}, {"ticks": n}
+    #[serde(default)]
+    excerpt_end: Option<ExcerptBound>, // 발췌 끝 (마디는 해당 마디까지 포함)
+    #[serde(flatten)]
+    filter: NoteFilter, // include_tracks, exclude_tracks, include_channels, ... (비어 있으면 필터 없음)
+}
+
+fn default_voice_count() -> usize {
+    MAX_VOICES
+}
+
+impl ConversionOptions {
+    fn allocation_options(&self) -> AllocationOptions {
+        AllocationOptions {
+            strategy: self.allocation,
+            overflow: self.overflow,
+            voice_count: self.voice_count,
+            melody: self.melody,
+            bass_part: self.bass_part,
+        }
+    }
+
+    // 옵션 값 검사 (변환 시작 전에 호출)
+    fn validate(&self) -> Result<(), String> {
+        if let Some((min_octave, max_octave)) = self.octave_range {
+            if min_octave > max_octave {
+                return Err(format!("옥타브 범위의 최저({})가 최고({})보다 높습니다", min_octave, max_octave));
+            }
+            if min_octave < MIN_PLAYABLE_OCTAVE || max_octave > MAX_PLAYABLE_OCTAVE {
+                return Err(format!(
+                    "옥타브 범위는 O{} ~ O{} 안이어야 합니다",
+                    MIN_PLAYABLE_OCTAVE, MAX_PLAYABLE_OCTAVE
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // 음역 검사 범위 (지정이 없으면 게임 기본 범위, 모든 악기 공통)
+    fn octave_range(&self) -> (i32, i32) {
+        self.octave_range.unwrap_or((MIN_PLAYABLE_OCTAVE, MAX_PLAYABLE_OCTAVE))
+    }
+
+    // 화면에 표시되는 파트 순서 (빈 파트 제외, 베이스는 맨 뒤)대로 파트별 조옮김 적용 후 음역 재적용
+    fn apply_part_transpose(&self, voices: Vec<Vec<Note>>) -> Vec<Vec<Note>> {
+        let octave_range = self.octave_range();
+
+        voices.into_iter()
+            .enumerate()
+            .map(|(idx, voice)| {
+                let semitones = match self.part_transpose.get(idx) {
+                    Some(Transpose::Semitones(n)) => *n,
+                    Some(Transpose::Auto) => auto_octave_shift(&voice, octave_range),
+                    None => 0,
+                };
+                if semitones == 0 {
+                    return voice;
+                }
+                let voice = transpose_notes(voice, semitones);
+                enforce_range(voice, self.range_policy, Some(octave_range)).0
+            })
+            .collect()
+    }
+
+    // fold/drop 정책이면 생성된 MML이 음역 안에 있는지 확인
+    fn verify_octaves(&self, mml: &str) -> Result<(), String> {
+        if self.range_policy == RangePolicy::Report {
+            return Ok(());
+        }
+        let (min_octave, max_octave) = self.octave_range();
+        check_mml_octaves(mml, min_octave, max_octave)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,6 +133,78 @@ struct VoiceResult {
     char_count: usize,
     note_count: usize,
     duration: f64,
+    #[serde(default)]
+    program: u8, // 파트 첫 음의 GM 프로그램
+    #[serde(default)]
+    notes: Vec<PartNote>, // 크롭 후 음표 (내보내기용)
+}
+
+impl VoiceResult {
+    fn export_part(&self) -> ExportPart {
+        ExportPart {
+            name: self.name.clone(),
+            program: self.program,
+            notes: self.notes.iter().map(|n| n.to_note(self.program)).collect(),
+        }
+    }
+}
+
+// 파트에 배분된 음 (크롭 후)
+#[derive(Debug, Serialize, Deserialize)]
+struct PartNote {
+    note: u8,
+    start: u32,
+    end: u32,
+    velocity: u8,
+}
+
+impl From<&Note> for PartNote {
+    fn from(note: &Note) -> Self {
+        PartNote {
+            note: note.note,
+            start: note.start,
+            end: note.end,
+            velocity: note.velocity,
+        }
+    }
+}
+
+impl PartNote {
+    fn to_note(&self, program: u8) -> Note {
+        Note {
+            note: self.note,
+            start: self.start,
+            end: self.end,
+            duration: self.end.saturating_sub(self.start),
+            velocity: self.velocity,
+            instrument: get_instrument_name(program),
+            track: 0,
+            channel: 0,
+            program,
+        }
+    }
+}
+
+// 멜로디로 선택된 음 (크롭 후)
+#[derive(Debug, Serialize, Deserialize)]
+struct MelodyNote {
+    note: u8,
+    start: u32,
+    end: u32,
+    track: usize,
+    channel: u8,
+}
+
+impl From<&Note> for MelodyNote {
+    fn from(note: &Note) -> Self {
+        MelodyNote {
+            note: note.note,
+            start: note.start,
+            end: note.end,
+            track: note.track,
+            channel: note.channel,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,18 +214,321 @@ struct ConversionResult {
     error: Option<String>,
     bpm: u32,
     total_notes: usize,
+    melody_notes: Vec<MelodyNote>,
+    out_of_range_notes: usize,
+    transpose: i32,
+    time_signatures: Vec<TimeSignature>, // 발췌 후 기준 박자표 (내보내기용)
 }
 
-#[tauri::command]
-fn convert_midi(midi_data: Vec<u8>, options: ConversionOptions) -> ConversionResult {
-    match convert_midi_internal(&midi_data, &options) {
-        Ok(result) => result,
-        Err(e) => ConversionResult {
+impl ConversionResult {
+    fn failed(error: String) -> Self {
+        ConversionResult {
             success: false,
             voices: vec![],
-            error: Some(e),
+            error: Some(error),
             bpm: 0,
             total_notes: 0,
+            melody_notes: vec![],
+            out_of_range_notes: 0,
+            transpose: 0,
+            time_signatures: vec![],
+        }
+    }
+}
+
+#[tauri::command]
+fn convert_midi(midi_data: Vec<u8>, options: ConversionOptions) -> ConversionResult {
+    convert_midi_internal(&midi_data, &options, &ConversionTask::detached()).unwrap_or_else(ConversionResult::failed)
+}
+
+const CANCELLED_MESSAGE: &str = "변환이 취소되었습니다";
+
+// "conversion-progress" 이벤트의 진행 단계
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ConversionStage {
+    Parsing,
+    Allocating,
+    Generating,
+    Cropping,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ConversionProgress {
+    task_id: u64,
+    stage: ConversionStage,
+    current: usize, // 단계 안에서 끝난 수 (파트별 생성/크롭 검사)
+    total: usize,
+}
+
+// 변환 한 건의 진행 알림과 취소 확인 (동기 명령은 알림/취소 없이 사용)
+struct ConversionTask {
+    id: u64,
+    cancelled: Arc<AtomicBool>,
+    app: Option<AppHandle>,
+}
+
+impl ConversionTask {
+    fn detached() -> Self {
+        ConversionTask {
+            id: 0,
+            cancelled: Arc::default(),
+            app: None,
+        }
+    }
+
+    // 같은 취소 토큰을 쓰지만 단계별 진행은 알리지 않는 작업 (일괄 변환의 파일 하나)
+    fn silent(&self) -> Self {
+        ConversionTask {
+            id: self.id,
+            cancelled: self.cancelled.clone(),
+            app: None,
+        }
+    }
+
+    // 취소됐으면 Err
+    fn check(&self) -> Result<(), String> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(CANCELLED_MESSAGE.to_string());
+        }
+        Ok(())
+    }
+
+    // 취소됐으면 Err, 아니면 진행 상황을 알림
+    fn report(&self, stage: ConversionStage, current: usize, total: usize) -> Result<(), String> {
+        self.check()?;
+        if let Some(app) = &self.app {
+            let progress = ConversionProgress {
+                task_id: self.id,
+                stage,
+                current,
+                total,
+            };
+            let _ = app.emit("conversion-progress", progress);
+        }
+        Ok(())
+    }
+}
+
+// 진행 중인 백그라운드 변환의 취소 토큰 (작업 id별)
+#[derive(Default)]
+struct ConversionTasks {
+    tokens: Mutex<HashMap<u64, Arc<AtomicBool>>>,
+}
+
+impl ConversionTasks {
+    // 새 작업 등록, 같은 id의 작업이 아직 진행 중이면 Err
+    fn register(&self, app: AppHandle, task_id: u64) -> Result<ConversionTask, String> {
+        let cancelled = match self.tokens.lock().unwrap().entry(task_id) {
+            Entry::Occupied(_) => return Err(format!("이미 진행 중인 작업 id입니다: {}", task_id)),
+            Entry::Vacant(entry) => entry.insert(Arc::default()).clone(),
+        };
+        Ok(ConversionTask {
+            id: task_id,
+            cancelled,
+            app: Some(app),
+        })
+    }
+
+    fn remove(&self, task_id: u64) {
+        self.tokens.lock().unwrap().remove(&task_id);
+    }
+}
+
+// 파일 하나를 백그라운드에서 변환 (형식은 파일 이름의 확장자로 판단)
+// 진행은 "conversion-progress" 이벤트, cancel_conversion으로 중단
+#[tauri::command]
+async fn start_conversion(
+    app: AppHandle,
+    tasks: State<'_, ConversionTasks>,
+    task_id: u64,
+    file_name: String,
+    data: Vec<u8>,
+    options: ConversionOptions,
+) -> Result<ConversionResult, String> {
+    let task = tasks.register(app, task_id)?;
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        convert_file_internal(&file_name, &data, &options, &task).unwrap_or_else(ConversionResult::failed)
+    })
+    .await
+    .map_err(|e| e.to_string());
+
+    tasks.remove(task_id);
+    result
+}
+
+// 진행 중인 변환에 취소 표시 (다음 진행 알림 지점에서 멈춤), 해당 작업이 없으면 false
+#[tauri::command]
+fn cancel_conversion(tasks: State<'_, ConversionTasks>, task_id: u64) -> bool {
+    match tasks.tokens.lock().unwrap().get(&task_id) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SourceListResult {
+    success: bool,
+    sources: Vec<NoteSource>,
+    error: Option<String>,
+}
+
+// 변환 전에 필터로 고를 수 있는 (트랙, 채널, 프로그램) 목록
+#[tauri::command]
+fn list_midi_sources(midi_data: Vec<u8>) -> SourceListResult {
+    // 중복 제거 전의 음으로 세어야 다른 트랙과 겹친 음도 각 트랙에 포함됨
+    match extract_raw_midi_notes(&midi_data, &no_progress) {
+        Ok((notes, _)) => SourceListResult {
+            success: true,
+            sources: list_note_sources(&notes),
+            error: None,
+        },
+        Err(e) => SourceListResult {
+            success: false,
+            sources: vec![],
+            error: Some(e),
+        },
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct InspectResult {
+    success: bool,
+    info: Option<MidiInfo>,
+    error: Option<String>,
+}
+
+// 파일 형식, 해상도, 템포/박자표, 트랙별 정보
+#[tauri::command]
+fn inspect_midi_file(midi_data: Vec<u8>) -> InspectResult {
+    match inspect_midi(&midi_data) {
+        Ok(info) => InspectResult {
+            success: true,
+            info: Some(info),
+            error: None,
+        },
+        Err(e) => InspectResult {
+            success: false,
+            info: None,
+            error: Some(e),
+        },
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportResult {
+    success: bool,
+    data: Vec<u8>,
+    error: Option<String>,
+}
+
+impl From<Result<Vec<u8>, String>> for ExportResult {
+    fn from(result: Result<Vec<u8>, String>) -> Self {
+        match result {
+            Ok(data) => ExportResult {
+                success: true,
+                data,
+                error: None,
+            },
+            Err(e) => ExportResult {
+                success: false,
+                data: vec![],
+                error: Some(e),
+            },
+        }
+    }
+}
+
+// 변환 결과의 파트들을 파트당 트랙 하나인 MIDI로 내보내기
+#[tauri::command]
+fn export_midi_file(voices: Vec<VoiceResult>, bpm: u32, time_signatures: Vec<TimeSignature>) -> ExportResult {
+    let parts: Vec<ExportPart> = voices.iter().map(VoiceResult::export_part).collect();
+    export_midi(&parts, bpm, &time_signatures).into()
+}
+
+// 변환 결과의 파트들을 MusicXML 악보로 내보내기 (파트 이름은 MML 파트 이름)
+#[tauri::command]
+fn export_musicxml_file(voices: Vec<VoiceResult>, bpm: u32, time_signatures: Vec<TimeSignature>) -> ExportResult {
+    let parts: Vec<ExportPart> = voices.iter().map(VoiceResult::export_part).collect();
+    export_musicxml(&parts, bpm, &time_signatures)
+        .map(String::into_bytes)
+        .into()
+}
+
+// 변환 결과를 MabiIcco 프로젝트로 내보내기 (세 파트씩 멜로디/화음1/화음2 트랙)
+// 악기별 모드 결과는 by_instrument로 악기마다 트랙을 나눔
+#[tauri::command]
+fn export_mmi_file(
+    voices: Vec<VoiceResult>,
+    title: String,
+    time_signatures: Vec<TimeSignature>,
+    by_instrument: bool,
+) -> ExportResult {
+    let parts: Vec<(String, u8)> = voices.iter().map(|v| (v.content.clone(), v.program)).collect();
+    let tracks = group_mmi_tracks(&parts, by_instrument);
+    export_mmi(&tracks, &title, &time_signatures)
+        .map(String::into_bytes)
+        .into()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MinifyResult {
+    success: bool,
+    content: String,
+    original_count: usize,
+    char_count: usize,
+    equivalent: bool, // 원본과 재생 타임라인이 같은지
+    error: Option<String>,
+}
+
+// 붙여넣은 MML (단일 파트 또는 MML@ 묶음)을 같은 소리의 가장 짧은 MML로
+#[tauri::command]
+fn minify_mml_text(mml_text: String) -> MinifyResult {
+    let minified = minify_mml(&mml_text)
+        .and_then(|content| mml_equivalent(&mml_text, &content).map(|equivalent| (content, equivalent)));
+    match minified {
+        Ok((content, equivalent)) => MinifyResult {
+            success: true,
+            original_count: mml_char_count(&mml_text),
+            char_count: mml_char_count(&content),
+            content,
+            equivalent,
+            error: None,
+        },
+        Err(e) => MinifyResult {
+            success: false,
+            content: String::new(),
+            original_count: mml_char_count(&mml_text),
+            char_count: 0,
+            equivalent: false,
+            error: Some(e),
+        },
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FormatResult {
+    success: bool,
+    content: String,
+    error: Option<String>,
+}
+
+// MML을 마디 단위로 나눠 보여주기 (공백, "|", 주석을 지우면 원래 MML)
+#[tauri::command]
+fn format_mml_text(mml_text: String, time_signatures: Vec<TimeSignature>, options: MmlFormatOptions) -> FormatResult {
+    match format_mml(&mml_text, &time_signatures, &options) {
+        Ok(content) => FormatResult {
+            success: true,
+            content,
+            error: None,
+        },
+        Err(e) => FormatResult {
+            success: false,
+            content: String::new(),
+            error: Some(e),
         },
     }
 }
@@ -53,16 +536,172 @@ fn convert_midi(midi_data: Vec<u8>, options: ConversionOptions) -> ConversionRes
 fn convert_midi_internal(
     midi_data: &[u8],
     options: &ConversionOptions,
+    task: &ConversionTask,
+) -> Result<ConversionResult, String> {
+    let (notes, bpm) = extract_raw_midi_notes(midi_data, &|current, total| {
+        task.report(ConversionStage::Parsing, current, total)
+    })?;
+    let time_signatures = read_time_signatures(midi_data)?;
+    convert_notes_internal(notes, bpm, &time_signatures, options, task)
+}
+
+// MML 입력 (MML@ 묶음 또는 단일 파트)을 다시 배분/생성
+#[tauri::command]
+fn convert_mml(mml_text: String, options: ConversionOptions) -> ConversionResult {
+    import_mml(&mml_text)
+        .and_then(|score| convert_score_internal(score, &options, &ConversionTask::detached()))
+        .unwrap_or_else(ConversionResult::failed)
+}
+
+// MusicXML (.musicxml 또는 압축된 .mxl) 입력
+#[tauri::command]
+fn convert_musicxml(data: Vec<u8>, options: ConversionOptions) -> ConversionResult {
+    import_musicxml(&data)
+        .and_then(|score| convert_score_internal(score, &options, &ConversionTask::detached()))
+        .unwrap_or_else(ConversionResult::failed)
+}
+
+// ABC 악보 입력 (첫 번째 곡)
+#[tauri::command]
+fn convert_abc(abc_text: String, options: ConversionOptions) -> ConversionResult {
+    import_abc(&abc_text)
+        .and_then(|score| convert_score_internal(score, &options, &ConversionTask::detached()))
+        .unwrap_or_else(ConversionResult::failed)
+}
+
+// 일괄 변환할 파일 하나 (형식은 확장자로 판단)
+#[derive(Debug, Serialize, Deserialize)]
+struct BatchFile {
+    name: String,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BatchFileResult {
+    name: String,
+    result: ConversionResult,
+}
+
+// 파일 하나가 끝날 때마다 보내는 "batch-progress" 이벤트
+#[derive(Debug, Clone, Serialize)]
+struct BatchProgress {
+    completed: usize,
+    total: usize,
+    name: String,
+}
+
+// 여러 파일을 스레드 풀에서 동시에 변환 (결과는 입력 순서대로, 실패한 파일은 그 파일의 error에)
+// cancel_conversion(task_id)로 중단하면 아직 끝나지 않은 파일은 취소 오류로 채움
+#[tauri::command]
+async fn convert_batch(
+    app: AppHandle,
+    tasks: State<'_, ConversionTasks>,
+    task_id: u64,
+    files: Vec<BatchFile>,
+    options: ConversionOptions,
+) -> Result<Vec<BatchFileResult>, String> {
+    let task = tasks.register(app.clone(), task_id)?;
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let total = files.len();
+        let completed = AtomicUsize::new(0);
+
+        files.par_iter()
+            .map(|file| {
+                let file_task = task.silent();
+                let result = file_task.check()
+                    .and_then(|_| convert_file_internal(&file.name, &file.data, &options, &file_task))
+                    .unwrap_or_else(ConversionResult::failed);
+                let progress = BatchProgress {
+                    completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+                    total,
+                    name: file.name.clone(),
+                };
+                let _ = app.emit("batch-progress", progress);
+                BatchFileResult {
+                    name: file.name.clone(),
+                    result,
+                }
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string());
+
+    tasks.remove(task_id);
+    result
+}
+
+fn convert_file_internal(
+    name: &str,
+    data: &[u8],
+    options: &ConversionOptions,
+    task: &ConversionTask,
+) -> Result<ConversionResult, String> {
+    let extension = Path::new(name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let text = || String::from_utf8_lossy(data).into_owned();
+
+    match extension.as_str() {
+        "mid" | "midi" => convert_midi_internal(data, options, task),
+        "mml" | "txt" => convert_score_internal(import_mml(&text())?, options, task),
+        "musicxml" | "xml" | "mxl" => convert_score_internal(import_musicxml(data)?, options, task),
+        "abc" => convert_score_internal(import_abc(&text())?, options, task),
+        _ => Err(format!("지원하지 않는 파일 형식: {}", name)),
+    }
+}
+
+// 텍스트/악보 형식은 읽기가 한 번에 끝나므로 읽은 뒤 진행 알림
+fn convert_score_internal(
+    score: ImportedScore,
+    options: &ConversionOptions,
+    task: &ConversionTask,
+) -> Result<ConversionResult, String> {
+    task.report(ConversionStage::Parsing, 1, 1)?;
+    convert_notes_internal(score.notes, score.bpm, &score.time_signatures, options, task)
+}
+
+// 입력 형식과 무관한 공통 변환 과정 (필터 → 스트럼 → 격자 맞춤 → 발췌 → 조옮김 → 음역 → 배분/생성)
+fn convert_notes_internal(
+    notes: Vec<Note>,
+    bpm: u32,
+    time_signatures: &[TimeSignature],
+    options: &ConversionOptions,
+    task: &ConversionTask,
 ) -> Result<ConversionResult, String> {
-    let (notes, bpm) = extract_midi_notes(midi_data, 24)?;
+    options.validate()?;
+    task.report(ConversionStage::Allocating, 0, 1)?;
+    // 필터는 격자 맞춤/중복 제거 전에 적용 (제외한 트랙의 음이 겹친 다른 트랙의 음을 대신 남기지 않도록)
+    let notes = filter_notes(notes, &options.filter);
+    // 스트럼은 실제 시작 간격으로 판단해야 하므로 격자에 맞추기 전에 합침
+    let notes = collapse_strums(notes, options.strum_tolerance);
+    let notes = normalize_notes(notes, options.grid);
+
+    // 발췌 구간이 있으면 잘라서 0부터 시작하도록 당김
+    let (notes, time_signatures) =
+        excerpt_notes(notes, options.excerpt_start, options.excerpt_end, bpm, time_signatures)?;
     let total_notes = notes.len();
 
-    let voices = if options.mode == "instrument" {
+    let transpose = match options.transpose {
+        Transpose::Semitones(n) => n,
+        Transpose::Auto => {
+            // 배분 결과는 조옮김과 무관하므로 한 번 배분해 보고 파트별 옥타브 명령 수로 평가
+            let trial = allocate_voices_with_progress(notes.clone(), &options.allocation_options(), &|_, _| task.check())?;
+            auto_transpose(&trial, options.octave_range())
+        }
+    };
+    let notes = transpose_notes(notes, transpose);
+
+    let (notes, out_of_range_notes) =
+        enforce_range(notes, options.range_policy, options.octave_range);
+
+    let (voices, melody_notes) = if options.mode == "instrument" {
         // 악기별 모드
-        convert_by_instrument(notes, bpm, options.char_limit, options.compress_mode)?
+        convert_by_instrument(notes, bpm, options, task)?
     } else {
         // 일반 모드 (피치별)
-        convert_by_pitch(notes, bpm, options.char_limit, options.compress_mode)?
+        convert_by_pitch(notes, bpm, options, task)?
     };
 
     Ok(ConversionResult {
@@ -71,24 +710,123 @@ fn convert_midi_internal(
         error: None,
         bpm,
         total_notes,
+        melody_notes,
+        out_of_range_notes,
+        transpose,
+        time_signatures,
     })
 }
 
+// 파트 하나를 한 번 생성한 결과 (앞의 음표 몇 개만 남긴 MML은 앞부분을 잘라서 얻음)
+struct GeneratedVoice {
+    mml: String,
+    note_ends: Vec<usize>,
+}
+
+impl GeneratedVoice {
+    fn new(voice: &[Note], bpm: u32, compress_mode: bool) -> Self {
+        let start_octave = voice.first().map_or(4, |n| ((n.note as i32 / 12) - 1).clamp(2, 6));
+        let (mml, note_ends) = generate_mml_incremental(voice, bpm, start_octave, compress_mode);
+        GeneratedVoice { mml, note_ends }
+    }
+
+    // 앞의 count개 음표만 남긴 MML (기본 길이는 파트 전체로 정한 것 그대로)
+    fn prefix(&self, count: usize) -> &str {
+        match count {
+            0 => "",
+            n => &self.mml[..self.note_ends[n - 1]],
+        }
+    }
+
+    // 크롭된 파트의 최종 MML: 남은 음표로 기본 길이를 다시 정해 한 번 더 생성 (generate_mml_final과 같은 결과)
+    // 다시 정한 기본 길이 때문에 char_limit을 넘으면 크롭 검사에 쓴 앞부분을 그대로 사용
+    fn cropped(&self, voice: &[Note], count: usize, bpm: u32, options: &ConversionOptions) -> String {
+        let prefix = self.prefix(count);
+        if count == 0 || count == voice.len() {
+            return prefix.to_string();
+        }
+        let regenerated = GeneratedVoice::new(&voice[..count], bpm, options.compress_mode).mml;
+        if mml_char_count(&regenerated) <= options.char_limit {
+            regenerated
+        } else {
+            prefix.to_string()
+        }
+    }
+
+    // char_limit 안에 들어가는 최대 음표 수 (글자 수는 음표 수에 따라 늘어나므로 이진 탐색)
+    fn fitting_notes(&self, options: &ConversionOptions) -> usize {
+        self.note_ends
+            .partition_point(|&end| mml_char_count(&self.mml[..end]) <= options.char_limit)
+    }
+}
+
+// 파트마다 MML을 동시에 생성 (파트 하나가 끝날 때마다 진행 알림)
+fn generate_voices(
+    voices: &[Vec<Note>],
+    bpm: u32,
+    options: &ConversionOptions,
+    task: &ConversionTask,
+) -> Result<Vec<GeneratedVoice>, String> {
+    let done = AtomicUsize::new(0);
+    voices.par_iter()
+        .map(|voice| {
+            let generated = GeneratedVoice::new(voice, bpm, options.compress_mode);
+            task.report(ConversionStage::Generating, done.fetch_add(1, Ordering::Relaxed) + 1, voices.len())?;
+            Ok(generated)
+        })
+        .collect()
+}
+
+// 모든 파트가 char_limit 이하가 되는 가장 늦은 크롭 시점 (격자 단위, 모두 들어가면 None)
+// 파트마다 처음으로 들어가지 못하는 음의 시작 중 가장 이른 시점 (파트별 검사는 동시에)
+fn find_crop_time(
+    voices: &[Vec<Note>],
+    generated: &[GeneratedVoice],
+    options: &ConversionOptions,
+    task: &ConversionTask,
+) -> Result<Option<u32>, String> {
+    let done = AtomicUsize::new(0);
+    let limits = voices.par_iter()
+        .zip(generated.par_iter())
+        .map(|(voice, generated)| {
+            let limit = voice.get(generated.fitting_notes(options)).map(|note| note.start / GRID_SIZE * GRID_SIZE);
+            task.report(ConversionStage::Cropping, done.fetch_add(1, Ordering::Relaxed) + 1, voices.len())?;
+            Ok(limit)
+        })
+        .collect::<Result<Vec<Option<u32>>, String>>()?;
+    Ok(limits.into_iter().flatten().min())
+}
+
 fn convert_by_pitch(
     notes: Vec<Note>,
     bpm: u32,
-    char_limit: usize,
-    compress_mode: bool,
-) -> Result<Vec<VoiceResult>, String> {
-    let voices = allocate_voices_smart(notes);
+    options: &ConversionOptions,
+    task: &ConversionTask,
+) -> Result<(Vec<VoiceResult>, Vec<MelodyNote>), String> {
+    let allocation = options.allocation_options();
+    let mut voices = allocate_voices_with_progress(notes, &allocation, &|current, total| {
+        task.report(ConversionStage::Allocating, current, total)
+    })?;
+
+    // 베이스 파트는 마지막 voice
+    let bass_voice = if allocation.has_bass_part() { voices.pop() } else { None };
+    let has_melody = voices.first().is_some_and(|v| !v.is_empty());
     
     // 빈 voice 제거
-    let voices: Vec<Vec<Note>> = voices.into_iter()
+    let mut voices: Vec<Vec<Note>> = voices.into_iter()
         .filter(|v| !v.is_empty())
         .collect();
+
+    let has_bass = matches!(&bass_voice, Some(bass) if !bass.is_empty());
+    if has_bass {
+        voices.extend(bass_voice);
+    }
+
+    let voices = options.apply_part_transpose(voices);
+    let melody_voice = if has_melody { voices[0].clone() } else { Vec::new() };
     
     if voices.is_empty() {
-        return Ok(Vec::new());
+        return Ok((Vec::new(), Vec::new()));
     }
     
     // 최대 end_time 찾기
@@ -99,72 +837,29 @@ fn convert_by_pitch(
         .unwrap_or(0);
     
     if max_end_time == 0 {
-        return Ok(Vec::new());
+        return Ok((Vec::new(), Vec::new()));
     }
     
-    // 이진 탐색으로 모든 voice가 char_limit 이하인 최대 end_time 찾기
-    let grid_size = 24u32;
-    let mut left = 0u32;
-    let mut right = max_end_time;
-    let mut best_end_time = max_end_time;
-    
-    while left <= right {
-        let mid = ((left + right) / 2 / grid_size) * grid_size;
-        
-        let mut all_valid = true;
-        
-        // 각 voice를 mid 시간까지 크롭해서 char_limit 체크
-        for voice in voices.iter() {
-            let cropped: Vec<Note> = voice.iter()
-                .filter(|n| n.start < mid)
-                .cloned()
-                .collect();
-            
-            if cropped.is_empty() {
-                continue;
-            }
-            
-            let first_note = cropped[0].note;
-            let mut start_octave = (first_note as i32 / 12) - 1;
-            start_octave = start_octave.max(2).min(6);
-            
-            let mml = generate_mml_final(&cropped, bpm, start_octave, compress_mode);
-            
-            if mml.len() > char_limit {
-                all_valid = false;
-                break;
-            }
-        }
-        
-        if all_valid {
-            best_end_time = mid;
-            left = mid + grid_size;
-        } else {
-            right = mid - grid_size;
-        }
-    }
-    
-    // best_end_time으로 모든 voice 최종 크롭
+    // 파트마다 MML을 한 번만 생성해 두고, 글자 수 제한에 처음 걸리는 시점에서 모든 파트를 크롭
+    let generated = generate_voices(&voices, bpm, options, task)?;
+    let best_end_time = find_crop_time(&voices, &generated, options, task)?.unwrap_or(max_end_time);
+
     let mut results = Vec::new();
-    for (idx, voice) in voices.iter().enumerate() {
-        let final_voice: Vec<Note> = voice.iter()
-            .filter(|n| n.start < best_end_time)
-            .cloned()
-            .collect();
-        
-        if final_voice.is_empty() {
+    for (idx, (voice, generated)) in voices.iter().zip(generated.iter()).enumerate() {
+        let count = voice.iter().take_while(|n| n.start < best_end_time).count();
+        if count == 0 {
             continue;
         }
 
-        let first_note = final_voice[0].note;
-        let mut start_octave = (first_note as i32 / 12) - 1;
-        start_octave = start_octave.max(2).min(6);
-
-        let mml_code = generate_mml_final(&final_voice, bpm, start_octave, compress_mode);
+        let final_voice = &voice[..count];
+        let mml_code = generated.cropped(voice, count, bpm, options);
+        options.verify_octaves(&mml_code)?;
         let note_count = final_voice.len();
         let end_time = best_end_time as f64 / TPB as f64 / 2.0;
 
-        let name = if idx == 0 {
+        let name = if has_bass && idx == voices.len() - 1 {
+            "베이스".to_string()
+        } else if idx == 0 {
             "멜로디".to_string()
         } else {
             format!("화음{}", idx)
@@ -173,21 +868,28 @@ fn convert_by_pitch(
         results.push(VoiceResult {
             name,
             content: mml_code.clone(),
-            char_count: mml_code.len(),
+            char_count: mml_char_count(&mml_code),
             note_count,
             duration: end_time,
+            program: final_voice[0].program,
+            notes: final_voice.iter().map(PartNote::from).collect(),
         });
     }
 
-    Ok(results)
+    let melody_notes = melody_voice.iter()
+        .filter(|n| n.start < best_end_time)
+        .map(MelodyNote::from)
+        .collect();
+
+    Ok((results, melody_notes))
 }
 
 fn convert_by_instrument(
     notes: Vec<Note>,
     bpm: u32,
-    char_limit: usize,
-    compress_mode: bool,
-) -> Result<Vec<VoiceResult>, String> {
+    options: &ConversionOptions,
+    task: &ConversionTask,
+) -> Result<(Vec<VoiceResult>, Vec<MelodyNote>), String> {
     let mut instrument_groups: HashMap<String, Vec<Note>> = HashMap::new();
     for note in notes {
         instrument_groups
@@ -202,22 +904,49 @@ fn convert_by_instrument(
     // 모든 악기의 voice 수집
     let mut all_voices = Vec::new();
     let mut voice_instrument_map = Vec::new();
+    let mut melody_voices = Vec::new(); // all_voices 안의 멜로디 파트 위치
+    let mut bass_voices = Vec::new();
     
-    for instrument_name in &instrument_names {
-        let instrument_notes = instrument_groups.get(instrument_name).unwrap();
-        let voices = allocate_voices_smart(instrument_notes.clone());
+    // 악기마다 배분은 서로 독립이므로 동시에 처리 (악기 하나가 끝날 때마다 진행 알림)
+    let allocation = options.allocation_options();
+    let done = AtomicUsize::new(0);
+    let allocated = instrument_names.par_iter()
+        .map(|instrument_name| {
+            let notes = instrument_groups[instrument_name].clone();
+            let voices = allocate_voices_with_progress(notes, &allocation, &|_, _| task.check())?;
+            task.report(ConversionStage::Allocating, done.fetch_add(1, Ordering::Relaxed) + 1, instrument_names.len())?;
+            Ok(voices)
+        })
+        .collect::<Result<Vec<Vec<Vec<Note>>>, String>>()?;
+
+    for (instrument_name, mut voices) in instrument_names.iter().zip(allocated) {
+        // 베이스 파트는 마지막 voice
+        let bass_voice = if allocation.has_bass_part() { voices.pop() } else { None };
+        if voices.first().is_some_and(|v| !v.is_empty()) {
+            melody_voices.push(all_voices.len());
+        }
 
         for voice in voices.into_iter() {
             if !voice.is_empty() {
                 all_voices.push(voice);
                 voice_instrument_map.push(instrument_name.clone());
+                bass_voices.push(false);
             }
         }
+
+        if let Some(bass) = bass_voice.filter(|v| !v.is_empty()) {
+            all_voices.push(bass);
+            voice_instrument_map.push(instrument_name.clone());
+            bass_voices.push(true);
+        }
     }
     
     if all_voices.is_empty() {
-        return Ok(Vec::new());
+        return Ok((Vec::new(), Vec::new()));
     }
+
+    // 파트별 조옮김은 모든 악기의 파트를 표시 순서대로 이어 붙인 번호로 적용
+    let all_voices = options.apply_part_transpose(all_voices);
     
     // 최대 end_time 찾기
     let max_end_time = all_voices.iter()
@@ -227,72 +956,33 @@ fn convert_by_instrument(
         .unwrap_or(0);
     
     if max_end_time == 0 {
-        return Ok(Vec::new());
-    }
-    
-    // 이진 탐색으로 모든 voice가 char_limit 이하인 최대 end_time 찾기
-    let grid_size = 24u32;
-    let mut left = 0u32;
-    let mut right = max_end_time;
-    let mut best_end_time = max_end_time;
-    
-    while left <= right {
-        let mid = ((left + right) / 2 / grid_size) * grid_size;
-        
-        let mut all_valid = true;
-        
-        // 각 voice를 mid 시간까지 크롭해서 char_limit 체크
-        for voice in all_voices.iter() {
-            let cropped: Vec<Note> = voice.iter()
-                .filter(|n| n.start < mid)
-                .cloned()
-                .collect();
-            
-            if cropped.is_empty() {
-                continue;
-            }
-            
-            let first_note = cropped[0].note;
-            let mut start_octave = (first_note as i32 / 12) - 1;
-            start_octave = start_octave.max(2).min(6);
-            
-            let mml = generate_mml_final(&cropped, bpm, start_octave, compress_mode);
-            
-            if mml.len() > char_limit {
-                all_valid = false;
-                break;
-            }
-        }
-        
-        if all_valid {
-            best_end_time = mid;
-            left = mid + grid_size;
-        } else {
-            right = mid - grid_size;
-        }
+        return Ok((Vec::new(), Vec::new()));
     }
     
-    // best_end_time으로 모든 voice 최종 크롭
+    // 파트마다 MML을 한 번만 생성해 두고, 글자 수 제한에 처음 걸리는 시점에서 모든 파트를 크롭
+    let generated = generate_voices(&all_voices, bpm, options, task)?;
+    let best_end_time = find_crop_time(&all_voices, &generated, options, task)?.unwrap_or(max_end_time);
+
     let mut results = Vec::new();
-    for (idx, (voice, instrument_name)) in all_voices.iter().zip(voice_instrument_map.iter()).enumerate() {
-        let final_voice: Vec<Note> = voice.iter()
-            .filter(|n| n.start < best_end_time)
-            .cloned()
-            .collect();
-        
-        if final_voice.is_empty() {
+    for (idx, ((voice, generated), instrument_name)) in all_voices.iter()
+        .zip(generated.iter())
+        .zip(voice_instrument_map.iter())
+        .enumerate()
+    {
+        let count = voice.iter().take_while(|n| n.start < best_end_time).count();
+        if count == 0 {
             continue;
         }
 
-        let first_note = final_voice[0].note;
-        let mut start_octave = (first_note as i32 / 12) - 1;
-        start_octave = start_octave.max(2).min(6);
-
-        let mml_code = generate_mml_final(&final_voice, bpm, start_octave, compress_mode);
+        let final_voice = &voice[..count];
+        let mml_code = generated.cropped(voice, count, bpm, options);
+        options.verify_octaves(&mml_code)?;
         let note_count = final_voice.len();
         let end_time = best_end_time as f64 / TPB as f64 / 2.0;
 
-        let name = if idx == 0 {
+        let name = if bass_voices[idx] {
+            format!("베이스 ({})", instrument_name)
+        } else if idx == 0 {
             format!("멜로디 ({})", instrument_name)
         } else {
             format!("화음{} ({})", idx, instrument_name)
@@ -301,20 +991,29 @@ fn convert_by_instrument(
         results.push(VoiceResult {
             name,
             content: mml_code.clone(),
-            char_count: mml_code.len(),
+            char_count: mml_char_count(&mml_code),
             note_count,
             duration: end_time,
+            program: final_voice[0].program,
+            notes: final_voice.iter().map(PartNote::from).collect(),
         });
     }
 
-    Ok(results)
+    let melody_notes = melody_voices.iter()
+        .flat_map(|&idx| all_voices[idx].iter())
+        .filter(|n| n.start < best_end_time)
+        .map(MelodyNote::from)
+        .collect();
+
+    Ok((results, melody_notes))
 }
 
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![convert_midi])
+        .manage(ConversionTasks::default())
+        .invoke_handler(tauri::generate_handler![convert_midi, convert_mml, convert_musicxml, convert_abc, list_midi_sources, inspect_midi_file, export_midi_file, export_musicxml_file, export_mmi_file, minify_mml_text, format_mml_text, convert_batch, start_conversion, cancel_conversion])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
\ No newline at end of file
@@ -0,0 +1,244 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::converter::Note;
+
+// 멜로디 선택 전략
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MelodyStrategy {
+    // 직전 멜로디에서 12반음 이내인 가장 높은 음 (기존 방식, 배분 중에 선택)
+    #[default]
+    Proximity,
+    // 각 시작 시점의 최고음
+    Skyline,
+    // 지정한 트랙의 최고음
+    Track(usize),
+    // 지정한 채널의 최고음
+    Channel(u8),
+    // 평균 벨로시티가 가장 높은 트랙/채널의 최고음
+    Velocity,
+    // 한 옥타브 이내 도약으로 이어지는 가장 긴 단선율
+    LongestLine,
+}
+
+// 멜로디 라인을 미리 뽑아 (멜로디, 나머지)로 분리
+pub fn extract_melody_line(notes: Vec<Note>, strategy: MelodyStrategy) -> (Vec<Note>, Vec<Note>) {
+    let selected: Vec<bool> = match strategy {
+        MelodyStrategy::Proximity => proximity(&notes),
+        MelodyStrategy::Skyline => skyline(&notes, |_| true),
+        MelodyStrategy::Track(track) => skyline(&notes, |n| n.track == track),
+        MelodyStrategy::Channel(channel) => skyline(&notes, |n| n.channel == channel),
+        MelodyStrategy::Velocity => {
+            let (track, channel) = loudest_stream(&notes);
+            skyline(&notes, |n| n.track == track && n.channel == channel)
+        }
+        MelodyStrategy::LongestLine => longest_line(&notes),
+    };
+
+    split_line(notes, selected)
+}
+
+// 곡 전체의 최저음 라인을 단선율 베이스 파트로 분리 (베이스, 나머지)
+// 다른 음과 함께 울리는 음만 베이스가 가져가므로 혼자 연주되는 선율은 나머지(멜로디)에 남음
+pub fn extract_bass_line(notes: Vec<Note>) -> (Vec<Note>, Vec<Note>) {
+    let selected = floor_line(&notes);
+    split_line(notes, selected)
+}
+
+// 선택된 음을 (라인, 나머지)로 나누고, 라인은 다음 음 시작 시점에서 잘라 단선율로 만듦
+fn split_line(notes: Vec<Note>, selected: Vec<bool>) -> (Vec<Note>, Vec<Note>) {
+    let mut line = Vec::new();
+    let mut rest = Vec::new();
+    for (note, is_selected) in notes.into_iter().zip(selected) {
+        if is_selected {
+            line.push(note);
+        } else {
+            rest.push(note);
+        }
+    }
+
+    line.sort_by_key(|n| n.start);
+    for i in 1..line.len() {
+        let next_start = line[i].start;
+        let prev = &mut line[i - 1];
+        if prev.end > next_start {
+            prev.end = next_start;
+            prev.duration = next_start - prev.start;
+        }
+    }
+
+    (line, rest)
+}
+
+// 조건을 만족하는 음 중 시작 시점별 최고음 선택
+// 더 높은 멜로디 음이 아직 울리고 있으면 그 시점은 건너뜀
+fn skyline(notes: &[Note], filter: impl Fn(&Note) -> bool) -> Vec<bool> {
+    outer_line(notes, |_, n| filter(n), |a, b| a > b)
+}
+
+// 다른 음이 함께 울리는 시작 시점의 최저음 선택 (skyline의 반대)
+fn floor_line(notes: &[Note]) -> Vec<bool> {
+    let accompanied = accompanied(notes);
+    outer_line(notes, |idx, _| accompanied[idx], |a, b| a < b)
+}
+
+// 음마다 시작 시점에 다른 음이 함께 울리는지 (같은 시점에 시작하거나 앞의 음이 아직 끝나지 않음)
+fn accompanied(notes: &[Note]) -> Vec<bool> {
+    let mut order: Vec<usize> = (0..notes.len()).collect();
+    order.sort_by_key(|&i| notes[i].start);
+
+    let mut result = vec![false; notes.len()];
+    let mut latest_end = 0;
+    for group in order.chunk_by(|&a, &b| notes[a].start == notes[b].start) {
+        let start = notes[group[0]].start;
+        let together = group.len() > 1 || latest_end > start;
+        for &idx in group {
+            result[idx] = together;
+            latest_end = latest_end.max(notes[idx].end);
+        }
+    }
+    result
+}
+
+// is_outer(a, b): a가 b보다 바깥쪽(최고음 라인이면 높은, 최저음 라인이면 낮은) 음인지
+fn outer_line(
+    notes: &[Note],
+    filter: impl Fn(usize, &Note) -> bool,
+    is_outer: impl Fn(u8, u8) -> bool,
+) -> Vec<bool> {
+    let mut top: HashMap<u32, usize> = HashMap::new();
+    for (idx, note) in notes.iter().enumerate() {
+        if !filter(idx, note) {
+            continue;
+        }
+        let entry = top.entry(note.start).or_insert(idx);
+        if is_outer(note.note, notes[*entry].note) {
+            *entry = idx;
+        }
+    }
+
+    let mut candidates: Vec<usize> = top.into_values().collect();
+    candidates.sort_by_key(|&i| notes[i].start);
+
+    let mut selected = vec![false; notes.len()];
+    let mut sounding: Option<usize> = None;
+    for idx in candidates {
+        let note = &notes[idx];
+        if let Some(prev) = sounding {
+            if notes[prev].end > note.start && !is_outer(note.note, notes[prev].note) {
+                continue;
+            }
+        }
+        selected[idx] = true;
+        sounding = Some(idx);
+    }
+    selected
+}
+
+// 시작 시점별로 직전 멜로디에서 12반음 이내인 최고음, 없으면 최고음 선택
+fn proximity(notes: &[Note]) -> Vec<bool> {
+    let mut onsets: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (idx, note) in notes.iter().enumerate() {
+        onsets.entry(note.start).or_default().push(idx);
+    }
+
+    let mut sorted_times: Vec<u32> = onsets.keys().copied().collect();
+    sorted_times.sort();
+
+    let mut selected = vec![false; notes.len()];
+    let mut last_melody_note: Option<u8> = None;
+
+    for start in sorted_times {
+        let group = &onsets[&start];
+        let highest = *group.iter().max_by_key(|&&i| notes[i].note).unwrap();
+
+        let chosen = last_melody_note
+            .and_then(|last| {
+                group
+                    .iter()
+                    .filter(|&&i| (notes[i].note as i32 - last as i32).abs() <= 12)
+                    .max_by_key(|&&i| notes[i].note)
+                    .copied()
+            })
+            .unwrap_or(highest);
+
+        selected[chosen] = true;
+        last_melody_note = Some(notes[chosen].note);
+    }
+
+    selected
+}
+
+// 평균 벨로시티가 가장 높은 (트랙, 채널)
+fn loudest_stream(notes: &[Note]) -> (usize, u8) {
+    let mut streams: HashMap<(usize, u8), (u32, u32)> = HashMap::new();
+    for note in notes {
+        let entry = streams.entry((note.track, note.channel)).or_default();
+        entry.0 += note.velocity as u32;
+        entry.1 += 1;
+    }
+
+    streams
+        .into_iter()
+        .max_by(|(ka, (sa, ca)), (kb, (sb, cb))| {
+            // 평균 비교 (sa/ca vs sb/cb), 동점이면 음이 많은 쪽, 그다음 앞 트랙
+            (*sa as u64 * *cb as u64)
+                .cmp(&(*sb as u64 * *ca as u64))
+                .then(ca.cmp(cb))
+                .then(kb.cmp(ka))
+        })
+        .map(|(key, _)| key)
+        .unwrap_or((0, 0))
+}
+
+// 총 발음 길이가 최대인 단선율 (겹치지 않고, 인접 음 간 도약 12반음 이내)
+fn longest_line(notes: &[Note]) -> Vec<bool> {
+    let mut order: Vec<usize> = (0..notes.len()).collect();
+    order.sort_by_key(|&i| (notes[i].start, Reverse(notes[i].note)));
+
+    // best[i]: i로 끝나는 선율의 최대 길이, prev[i]: 직전 음
+    let mut best = vec![0u64; notes.len()];
+    let mut prev: Vec<Option<usize>> = vec![None; notes.len()];
+
+    // 이미 끝난 음 중 음높이별 최선의 선율 끝
+    let mut finished_by_pitch: Vec<Option<usize>> = vec![None; 128];
+    let mut pending: BinaryHeap<Reverse<(u32, usize)>> = BinaryHeap::new();
+
+    for &j in &order {
+        let note = &notes[j];
+
+        while let Some(&Reverse((end, i))) = pending.peek() {
+            if end > note.start {
+                break;
+            }
+            pending.pop();
+            let pitch = notes[i].note as usize;
+            if finished_by_pitch[pitch].is_none_or(|k| best[k] < best[i]) {
+                finished_by_pitch[pitch] = Some(i);
+            }
+        }
+
+        let low = note.note.saturating_sub(12) as usize;
+        let high = (note.note as usize + 12).min(127);
+        let predecessor = finished_by_pitch[low..=high]
+            .iter()
+            .flatten()
+            .copied()
+            .max_by_key(|&i| best[i]);
+
+        best[j] = note.duration as u64 + predecessor.map_or(0, |i| best[i]);
+        prev[j] = predecessor;
+        pending.push(Reverse((note.end, j)));
+    }
+
+    let mut selected = vec![false; notes.len()];
+    let mut current = (0..notes.len()).max_by_key(|&i| best[i]);
+    while let Some(i) = current {
+        selected[i] = true;
+        current = prev[i];
+    }
+    selected
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+
+use crate::converter::TPB;
+
+// 박자표 분모 지수의 최댓값 (2^6 = 64분음표)
+const MAX_DENOMINATOR_EXPONENT: u8 = 6;
+
+// 박자표 변경 (tick은 TPB 기준)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeSignature {
+    pub tick: u32,
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+impl TimeSignature {
+    pub fn common_time(tick: u32) -> Self {
+        TimeSignature {
+            tick,
+            numerator: 4,
+            denominator: 4,
+        }
+    }
+
+    pub fn measure_ticks(&self) -> u32 {
+        (self.numerator as u64 * TPB as u64 * 4 / self.denominator.max(1) as u64).clamp(1, u32::MAX as u64) as u32
+    }
+}
+
+// 정렬하고 같은 tick은 마지막 것만 남기며, 0 tick에 박자표가 없으면 4/4를 넣음
+pub fn normalize_time_signatures(mut signatures: Vec<TimeSignature>) -> Vec<TimeSignature> {
+    signatures.retain(|s| s.numerator > 0 && s.denominator > 0);
+    signatures.sort_by_key(|s| s.tick);
+
+    let mut normalized: Vec<TimeSignature> = Vec::new();
+    for signature in signatures {
+        match normalized.last_mut() {
+            Some(last) if last.tick == signature.tick => *last = signature,
+            Some(last) if (last.numerator, last.denominator) == (signature.numerator, signature.denominator) => {}
+            _ => normalized.push(signature),
+        }
+    }
+
+    if normalized.first().is_none_or(|s| s.tick > 0) {
+        normalized.insert(0, TimeSignature::common_time(0));
+    }
+    normalized
+}
+
+// MIDI 박자표의 분모는 2의 지수로 저장됨
+// 64분음표보다 짧은 박은 손상된 파일로 보고 None (마디가 몇 tick으로 쪼개지지 않도록)
+pub fn decode_denominator(exponent: u8) -> Option<u32> {
+    (exponent <= MAX_DENOMINATOR_EXPONENT).then(|| 1 << exponent)
+}
+
+// MIDI 파일의 박자표 목록 (TPB 기준으로 변환)
+pub fn read_time_signatures(midi_data: &[u8]) -> Result<Vec<TimeSignature>, String> {
+    let smf = midly::Smf::parse(midi_data).map_err(|e| format!("MIDI 파싱 오류: {}", e))?;
+    let tpb = match smf.header.timing {
+        midly::Timing::Metrical(t) => t.as_int() as u32,
+        _ => return Err("SMPTE 타이밍 지원하지 않음".to_string()),
+    };
+
+    let mut signatures = Vec::new();
+    for track in &smf.tracks {
+        let mut tick = 0u32;
+        for event in track {
+            tick += event.delta.as_int();
+            if let midly::TrackEventKind::Meta(midly::MetaMessage::TimeSignature(numerator, denominator, _, _)) =
+                event.kind
+            {
+                let Some(denominator) = decode_denominator(denominator) else {
+                    continue;
+                };
+                signatures.push(TimeSignature {
+                    tick: ((tick as f64 * TPB as f64) / tpb as f64).round() as u32,
+                    numerator: numerator as u32,
+                    denominator,
+                });
+            }
+        }
+    }
+
+    Ok(normalize_time_signatures(signatures))
+}
+
+// tick에서 시작하는 마디의 다음 마디 시작
+// 마디 중간에 박자표가 바뀌면 그 지점에서 새 마디를 시작
+fn next_measure_start(signatures: &[TimeSignature], tick: u32) -> u32 {
+    let current = signatures
+        .iter()
+        .rev()
+        .find(|s| s.tick <= tick)
+        .copied()
+        .unwrap_or(TimeSignature::common_time(0));
+    let next_change = signatures.iter().find(|s| s.tick > tick).map_or(u32::MAX, |s| s.tick);
+    tick.saturating_add(current.measure_ticks()).min(next_change)
+}
+
+// end_tick 이상이 될 때까지의 마디 시작 tick 목록
+pub fn measure_starts(signatures: &[TimeSignature], end_tick: u32) -> Vec<u32> {
+    let mut starts = vec![0];
+    let mut tick = 0;
+    while tick < end_tick {
+        tick = next_measure_start(signatures, tick);
+        starts.push(tick);
+    }
+    starts
+}
+
+// index번째(0부터) 마디의 시작 tick (박자표가 바뀌기 전까지의 마디는 한 번에 셈)
+pub fn measure_start(signatures: &[TimeSignature], index: u32) -> u32 {
+    let signatures = normalize_time_signatures(signatures.to_vec());
+    let mut remaining = index as u64;
+    for (i, signature) in signatures.iter().enumerate() {
+        let Some(next) = signatures.get(i + 1) else {
+            break;
+        };
+        // 다음 박자표 직전의 잘린 마디도 한 마디
+        let measure_ticks = signature.measure_ticks() as u64;
+        let measures = ((next.tick - signature.tick) as u64).div_ceil(measure_ticks);
+        if remaining < measures {
+            return signature.tick + (remaining * measure_ticks) as u32;
+        }
+        remaining -= measures;
+    }
+
+    let last = signatures[signatures.len() - 1];
+    (last.tick as u64 + remaining * last.measure_ticks() as u64).min(u32::MAX as u64) as u32
+}
+
+// 발췌 시작점 기준으로 박자표를 당김 (시작점 이전의 마지막 박자표는 0으로)
+pub fn rebase_time_signatures(signatures: &[TimeSignature], start_tick: u32) -> Vec<TimeSignature> {
+    let rebased = signatures
+        .iter()
+        .map(|s| TimeSignature {
+            tick: s.tick.saturating_sub(start_tick),
+            ..*s
+        })
+        .collect();
+    normalize_time_signatures(rebased)
+}
@@ -0,0 +1,549 @@
+use std::collections::HashMap;
+
+use crate::converter::TPB;
+use crate::import::mml::{dotted, parse_timeline, split_mml_blocks, split_mml_parts, MmlSegment, MmlTimeline};
+use crate::utils::mml::midi_to_note_name;
+
+// 길이 숫자로 쓸 수 있는 값 (온음표를 나눴을 때 tick이 정수인 것만)
+const DENOMINATORS: [u32; 12] = [1, 2, 3, 4, 6, 8, 12, 16, 24, 32, 48, 64];
+
+// 길이 표를 미리 계산할 최대 길이, 이보다 긴 음은 온음표를 먼저 떼어 냄
+const TABLE_TICKS: u32 = TPB * 16;
+
+// 옥타브 상태 범위 (-1은 O0에서 <로만 갈 수 있음)
+const MIN_OCTAVE: i32 = -1;
+const OCTAVE_STATES: usize = 11;
+
+const INF: u32 = u32::MAX / 4;
+
+// MML을 재생 결과가 같은 가장 짧은 문자열로 다시 씀
+// "MML@a,b,c;" 묶음은 묶음마다 파트별로 줄여 다시 묶고 (묶음 밖의 글자는 그대로), 더 짧아지지 않는 파트는 공백만 제거해 그대로 둠
+pub fn minify_mml(text: &str) -> Result<String, String> {
+    if split_mml_parts(text).is_empty() {
+        return Err("MML 내용이 없습니다".to_string());
+    }
+    if !text.contains("MML@") {
+        return minify_part(text).map_err(|e| format!("파트 1: {}", e));
+    }
+
+    let mut minified = String::new();
+    let mut index = 0;
+    for segment in split_mml_blocks(text) {
+        match segment {
+            MmlSegment::Text(other) => minified.push_str(&other),
+            MmlSegment::Block { parts, closed } => {
+                let mut block = Vec::new();
+                for part in &parts {
+                    index += 1;
+                    block.push(minify_part(part).map_err(|e| format!("파트 {}: {}", index, e))?);
+                }
+                minified.push_str("MML@");
+                minified.push_str(&block.join(","));
+                if closed {
+                    minified.push(';');
+                }
+            }
+        }
+    }
+    Ok(minified)
+}
+
+// 두 MML의 파트 수와 파트별 타임라인 (음 높이, 시작/끝, 음량, 템포 변경)이 같은지
+pub fn mml_equivalent(a: &str, b: &str) -> Result<bool, String> {
+    let (a, b) = (split_mml_parts(a), split_mml_parts(b));
+    if a.len() != b.len() {
+        return Ok(false);
+    }
+    for (x, y) in a.iter().zip(b.iter()) {
+        if parse_timeline(x)? != parse_timeline(y)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn minify_part(part: &str) -> Result<String, String> {
+    let original: String = part.chars().filter(|c| !c.is_whitespace()).collect();
+    let timeline = parse_timeline(&original)?;
+
+    // 정수 tick 길이로 나타낼 수 없는 음이 있거나 검증에 실패하면 원본 유지
+    let Some(encoded) = encode_timeline(&timeline) else {
+        return Ok(original);
+    };
+    if encoded.len() >= original.len() || parse_timeline(&encoded).ok().as_ref() != Some(&timeline) {
+        return Ok(original);
+    }
+    Ok(encoded)
+}
+
+// 길이 하나 (숫자와 점, 생성기처럼 점은 하나까지만 씀)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Length {
+    ticks: u32,
+    denominator: u32,
+    dots: u32,
+}
+
+impl Length {
+    fn text(&self) -> String {
+        format!("{}{}", self.denominator, ".".repeat(self.dots as usize))
+    }
+
+    // 기본 길이가 default일 때 이 길이를 쓰는 데 필요한 글자
+    fn text_with_default(&self, default: &Length) -> String {
+        if self == default {
+            String::new()
+        } else if default.dots == 0 && self.denominator == default.denominator {
+            ".".repeat(self.dots as usize)
+        } else {
+            self.text()
+        }
+    }
+}
+
+fn all_lengths() -> Vec<Length> {
+    let mut lengths = Vec::new();
+    for denominator in DENOMINATORS {
+        for dots in 0..=1 {
+            let ticks = dotted((TPB * 4) as f64 / denominator as f64, dots);
+            if ticks.fract() == 0.0 {
+                lengths.push(Length {
+                    ticks: ticks as u32,
+                    denominator,
+                    dots,
+                });
+            }
+        }
+    }
+    lengths
+}
+
+// 기본 길이 하나와 길이 조각당 추가 글자 수(overhead)에 대한 최소 글자 수 표
+// cost[d]는 d ticks를 조각들로 나눠 쓸 때 (조각 글자 + overhead)의 합
+struct LengthTable {
+    cost: Vec<u32>,
+    choice: Vec<usize>,
+}
+
+struct LengthCoder {
+    lengths: Vec<Length>,
+    defaults: Vec<usize>, // L 명령으로 쓸 수 있는 길이
+    whole: usize,
+    piece_costs: Vec<Vec<u32>>, // [조각][기본 길이] 조각 글자 수
+    tables: HashMap<(usize, u32), LengthTable>,
+}
+
+impl LengthCoder {
+    fn new() -> Self {
+        let lengths = all_lengths();
+        let defaults = (0..lengths.len()).collect();
+        let whole = lengths
+            .iter()
+            .position(|l| l.denominator == 1 && l.dots == 0)
+            .unwrap_or(0);
+        let piece_costs = lengths
+            .iter()
+            .map(|piece| lengths.iter().map(|default| piece.text_with_default(default).len() as u32).collect())
+            .collect();
+        LengthCoder {
+            lengths,
+            defaults,
+            whole,
+            piece_costs,
+            tables: HashMap::new(),
+        }
+    }
+
+    fn piece_cost(&self, piece: usize, default: usize) -> u32 {
+        self.piece_costs[piece][default]
+    }
+
+    fn table(&mut self, default: usize, overhead: u32) -> &LengthTable {
+        if !self.tables.contains_key(&(default, overhead)) {
+            let size = TABLE_TICKS as usize + 1;
+            let mut cost = vec![INF; size];
+            let mut choice = vec![0; size];
+            cost[0] = 0;
+            for ticks in 1..size {
+                for (piece, length) in self.lengths.iter().enumerate() {
+                    let Some(rest) = ticks.checked_sub(length.ticks as usize) else {
+                        continue;
+                    };
+                    let total = cost[rest] + self.piece_cost(piece, default) + overhead;
+                    if total < cost[ticks] {
+                        cost[ticks] = total;
+                        choice[ticks] = piece;
+                    }
+                }
+            }
+            self.tables.insert((default, overhead), LengthTable { cost, choice });
+        }
+        &self.tables[&(default, overhead)]
+    }
+
+    // 표보다 긴 길이는 온음표를 먼저 떼어 냄 (앞에서부터 온음표 개수, 나머지)
+    fn split_long(&self, ticks: u32) -> (u32, u32) {
+        let whole = self.lengths[self.whole].ticks;
+        if ticks <= TABLE_TICKS {
+            return (0, ticks);
+        }
+        let count = (ticks - TABLE_TICKS).div_ceil(whole);
+        (count, ticks - count * whole)
+    }
+
+    fn cost(&mut self, ticks: u32, default: usize, overhead: u32) -> u32 {
+        let (wholes, rest) = self.split_long(ticks);
+        let whole_cost = self.piece_cost(self.whole, default) + overhead;
+        let rest_cost = self.table(default, overhead).cost[rest as usize];
+        rest_cost.saturating_add(wholes * whole_cost).min(INF)
+    }
+
+    fn pieces(&mut self, ticks: u32, default: usize, overhead: u32) -> Vec<usize> {
+        let (wholes, mut rest) = self.split_long(ticks);
+        let mut pieces = vec![self.whole; wholes as usize];
+        self.table(default, overhead);
+        let table = &self.tables[&(default, overhead)];
+        while rest > 0 {
+            let piece = table.choice[rest as usize];
+            pieces.push(piece);
+            rest -= self.lengths[piece].ticks;
+        }
+        pieces
+    }
+}
+
+// 타임라인을 재생 순서의 사건으로 (음과 음 사이는 쉼표, 음 도중의 템포 변경은 타이로 나눔)
+#[derive(Debug, Clone, Copy)]
+enum Event {
+    Rest(u32),
+    Note { pitch: u8, ticks: u32, volume: Option<u32>, tied: bool },
+    Tempo(u32),
+}
+
+fn timeline_events(timeline: &MmlTimeline) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut cursor = 0;
+    let mut tempos = timeline.tempos.iter().peekable();
+
+    let rest_until = |events: &mut Vec<Event>, cursor: &mut u32, tick: u32| {
+        if tick > *cursor {
+            events.push(Event::Rest(tick - *cursor));
+            *cursor = tick;
+        }
+    };
+
+    for note in &timeline.notes {
+        while let Some(&&(tick, tempo)) = tempos.peek().filter(|t| t.0 <= note.start) {
+            rest_until(&mut events, &mut cursor, tick);
+            events.push(Event::Tempo(tempo));
+            tempos.next();
+        }
+        rest_until(&mut events, &mut cursor, note.start);
+
+        let mut tied = false;
+        while let Some(&&(tick, tempo)) = tempos.peek().filter(|t| t.0 < note.end) {
+            events.push(Event::Note { pitch: note.pitch, ticks: tick - cursor, volume: note.volume, tied });
+            events.push(Event::Tempo(tempo));
+            cursor = tick;
+            tied = true;
+            tempos.next();
+        }
+        events.push(Event::Note { pitch: note.pitch, ticks: note.end - cursor, volume: note.volume, tied });
+        cursor = note.end;
+    }
+
+    for &(tick, tempo) in tempos {
+        rest_until(&mut events, &mut cursor, tick);
+        events.push(Event::Tempo(tempo));
+    }
+    events
+}
+
+fn digits(value: u32) -> u32 {
+    value.to_string().len() as u32
+}
+
+fn octave_of(pitch: u8) -> i32 {
+    pitch as i32 / 12 - 1
+}
+
+// 옥타브 이동: < > 반복과 O 명령 중 짧은 쪽
+fn octave_change(from: i32, to: i32) -> String {
+    if from == to {
+        return String::new();
+    }
+    let relative = (from - to).unsigned_abs();
+    if to < 0 || relative <= 1 + digits(to as u32) {
+        let step = if to > from { ">" } else { "<" };
+        step.repeat(relative as usize)
+    } else {
+        format!("O{}", to)
+    }
+}
+
+// 음 하나를 쓰는 방법 (이름 + 길이 조각들, 또는 기본 길이와 같을 때 N 번호)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NoteForm {
+    Named,
+    Number,
+}
+
+// 상태 = (옥타브, 기본 길이), 사건마다 옥타브/L 명령을 바꿔 가며 가장 짧은 경로를 찾음
+fn encode_timeline(timeline: &MmlTimeline) -> Option<String> {
+    let events = timeline_events(timeline);
+    let mut coder = LengthCoder::new();
+    let defaults = coder.defaults.clone();
+    let lengths_count = defaults.len();
+    let states = OCTAVE_STATES * lengths_count;
+    let state = |octave: usize, default: usize| octave * lengths_count + default;
+
+    let default_change: Vec<Vec<u32>> = defaults
+        .iter()
+        .map(|&from| {
+            defaults
+                .iter()
+                .map(|&to| if from == to { 0 } else { 1 + coder.lengths[to].text().len() as u32 })
+                .collect()
+        })
+        .collect();
+    let octave_cost: Vec<Vec<u32>> = (0..OCTAVE_STATES)
+        .map(|from| {
+            (0..OCTAVE_STATES)
+                .map(|to| octave_change(from as i32 + MIN_OCTAVE, to as i32 + MIN_OCTAVE).len() as u32)
+                .collect()
+        })
+        .collect();
+
+    // 파서 기본값: O4, L4
+    let quarter = defaults.iter().position(|&i| coder.lengths[i].ticks == TPB)?;
+    let mut cost = vec![INF; states];
+    cost[state((4 - MIN_OCTAVE) as usize, quarter)] = 0;
+
+    let mut fixed_cost = 0;
+    let mut volume = None;
+    let mut back: Vec<Vec<(u16, NoteForm)>> = Vec::with_capacity(events.len());
+
+    for event in &events {
+        let mut next = vec![INF; states];
+        let mut from = vec![(0u16, NoteForm::Named); states];
+
+        match *event {
+            Event::Tempo(tempo) => {
+                fixed_cost += 1 + digits(tempo);
+                back.push((0..states).map(|s| (s as u16, NoteForm::Named)).collect());
+                continue;
+            }
+            Event::Rest(ticks) => {
+                for to in 0..lengths_count {
+                    let rest = coder.cost(ticks, defaults[to], 1);
+                    for octave in 0..OCTAVE_STATES {
+                        for prev in 0..lengths_count {
+                            let total = cost[state(octave, prev)] + default_change[prev][to] + rest;
+                            if total < next[state(octave, to)] {
+                                next[state(octave, to)] = total;
+                                from[state(octave, to)] = (state(octave, prev) as u16, NoteForm::Named);
+                            }
+                        }
+                    }
+                }
+            }
+            Event::Note { pitch, ticks, volume: note_volume, tied } => {
+                if ticks == 0 {
+                    return None;
+                }
+                if note_volume != volume {
+                    fixed_cost += note_volume.map_or(0, |v| 1 + digits(v));
+                    volume = note_volume;
+                }
+
+                // 옥타브 이동 후 (옥타브, 이전 기본 길이)별 최소 비용
+                let mut moved = vec![(INF, 0usize); states];
+                for to_octave in 0..OCTAVE_STATES {
+                    for default in 0..lengths_count {
+                        for prev_octave in 0..OCTAVE_STATES {
+                            let total = cost[state(prev_octave, default)] + octave_cost[prev_octave][to_octave];
+                            if total < moved[state(to_octave, default)].0 {
+                                moved[state(to_octave, default)] = (total, prev_octave);
+                            }
+                        }
+                    }
+                }
+
+                let name_length = midi_to_note_name(pitch).0.len() as u32;
+                let overhead = 1 + name_length; // 타이 조각마다 "&" + 음 이름
+                let pitch_octave = (octave_of(pitch) - MIN_OCTAVE) as usize;
+                let number_cost = (pitch >= 12).then(|| tied as u32 + 1 + digits(pitch as u32 - 12));
+
+                for to in 0..lengths_count {
+                    let pieces = coder.cost(ticks, defaults[to], overhead);
+                    for octave in 0..OCTAVE_STATES {
+                        // 첫 조각에는 "&"가 붙지 않음
+                        let named = match pieces {
+                            pieces if octave == pitch_octave && pieces < INF => pieces - !tied as u32,
+                            _ => INF,
+                        };
+                        let number = number_cost
+                            .filter(|_| coder.lengths[defaults[to]].ticks == ticks)
+                            .unwrap_or(INF);
+                        let (note_cost, form) = if number < named {
+                            (number, NoteForm::Number)
+                        } else {
+                            (named, NoteForm::Named)
+                        };
+                        if note_cost >= INF {
+                            continue;
+                        }
+
+                        for prev in 0..lengths_count {
+                            let (moved_cost, prev_octave) = moved[state(octave, prev)];
+                            let total = moved_cost + default_change[prev][to] + note_cost;
+                            if total < next[state(octave, to)] {
+                                next[state(octave, to)] = total;
+                                from[state(octave, to)] = (state(prev_octave, prev) as u16, form);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        cost = next.into_iter().map(|c| c.min(INF)).collect();
+        back.push(from);
+    }
+
+    let (mut current, &best) = cost.iter().enumerate().min_by_key(|&(_, c)| *c)?;
+    if best + fixed_cost >= INF {
+        return None;
+    }
+
+    // 뒤에서부터 각 사건 직후의 상태를 복원
+    let mut path = vec![(0usize, NoteForm::Named); events.len()];
+    for index in (0..events.len()).rev() {
+        let (prev, form) = back[index][current];
+        path[index] = (current, form);
+        current = prev as usize;
+    }
+
+    let mut mml = String::new();
+    let mut octave = 4;
+    let mut default = quarter;
+    let mut volume = None;
+    for (event, &(after, form)) in events.iter().zip(path.iter()) {
+        let (to_octave, to_default) = (after / lengths_count, after % lengths_count);
+        let to_octave = to_octave as i32 + MIN_OCTAVE;
+
+        if let Event::Tempo(tempo) = *event {
+            mml.push_str(&format!("T{}", tempo));
+            continue;
+        }
+
+        mml.push_str(&octave_change(octave, to_octave));
+        octave = to_octave;
+        if to_default != default {
+            mml.push_str(&format!("L{}", coder.lengths[defaults[to_default]].text()));
+            default = to_default;
+        }
+        let default_length = coder.lengths[defaults[default]];
+
+        match *event {
+            Event::Rest(ticks) => {
+                for piece in coder.pieces(ticks, defaults[default], 1) {
+                    mml.push('R');
+                    mml.push_str(&coder.lengths[piece].text_with_default(&default_length));
+                }
+            }
+            Event::Note { pitch, ticks, volume: note_volume, tied } => {
+                if ticks == 0 {
+                    return None;
+                }
+                if note_volume != volume {
+                    if let Some(v) = note_volume {
+                        mml.push_str(&format!("V{}", v));
+                    }
+                    volume = note_volume;
+                }
+                if tied {
+                    mml.push('&');
+                }
+                let name = midi_to_note_name(pitch).0;
+                if form == NoteForm::Number {
+                    mml.push_str(&format!("N{}", pitch - 12));
+                    continue;
+                }
+                let overhead = 1 + name.len() as u32;
+                for (i, piece) in coder.pieces(ticks, defaults[default], overhead).into_iter().enumerate() {
+                    if i > 0 {
+                        mml.push('&');
+                    }
+                    mml.push_str(&name);
+                    mml.push_str(&coder.lengths[piece].text_with_default(&default_length));
+                }
+            }
+            Event::Tempo(_) => {}
+        }
+    }
+
+    Some(mml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converter::{generate_mml_final, Note};
+
+    fn note(pitch: u8, start: u32, end: u32) -> Note {
+        Note {
+            note: pitch,
+            start,
+            end,
+            duration: end - start,
+            velocity: 100,
+            instrument: "Piano".to_string(),
+            track: 0,
+            channel: 0,
+            program: 0,
+        }
+    }
+
+    // 옥타브를 오가는 선율, 점음표, 쉼표, 긴 음이 섞인 파트
+    fn sample_voice() -> Vec<Note> {
+        let pitches = [60, 64, 67, 72, 71, 67, 64, 55, 57, 59, 60, 84, 48, 62, 65, 69];
+        let lengths = [96, 96, 192, 288, 96, 384, 48, 48, 96, 192, 576, 96, 96, 1536, 144, 48];
+        let mut voice = Vec::new();
+        let mut tick = 0;
+        for (i, (&pitch, &length)) in pitches.iter().zip(lengths.iter()).enumerate() {
+            voice.push(note(pitch, tick, tick + length));
+            tick += length + if i % 5 == 4 { 192 } else { 0 };
+        }
+        voice
+    }
+
+    #[test]
+    fn generator_output_is_equivalent_and_shorter() {
+        for compress_mode in [false, true] {
+            let generated = generate_mml_final(&sample_voice(), 120, 4, compress_mode);
+            let minified = minify_mml(&generated).unwrap();
+            assert!(mml_equivalent(&generated, &minified).unwrap(), "{} -> {}", generated, minified);
+            assert!(minified.len() < generated.len(), "{} -> {}", generated, minified);
+        }
+    }
+
+    #[test]
+    fn never_writes_double_dots() {
+        for text in ["T120L16CCCCR4R4R4O5C2.&C8", "L8C&C&C&C&C&C&C", "C2.&C4.&C8R2R4R8"] {
+            let minified = minify_mml(text).unwrap();
+            assert!(!minified.contains(".."), "{} -> {}", text, minified);
+            assert!(mml_equivalent(text, &minified).unwrap());
+        }
+    }
+
+    #[test]
+    fn keeps_each_mml_block_and_surrounding_text() {
+        let part = generate_mml_final(&sample_voice(), 120, 4, false);
+        let text = format!("A MML@{0},{0};B MML@{0};", part);
+        let minified = minify_mml(&text).unwrap();
+        let short = minify_mml(&part).unwrap();
+        assert_eq!(minified, format!("A MML@{0},{0};B MML@{0};", short));
+        assert!(mml_equivalent(&text, &minified).unwrap());
+    }
+}
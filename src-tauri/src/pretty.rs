@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+
+use crate::converter::TPB;
+use crate::import::mml::{parse_commands, split_mml_blocks, split_mml_parts, MmlSegment};
+use crate::meter::{measure_starts, TimeSignature};
+
+// MML 보기 좋게 나누기 옵션
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MmlFormatOptions {
+    pub bar_markers: bool,        // 마디 사이에 "|" 표시
+    pub measures_per_line: usize, // 한 줄에 넣을 마디 수 (0이면 줄바꿈 없음)
+    pub annotate_time: bool,      // 마디마다 /* 마디 번호 분:초 */ 주석
+}
+
+impl Default for MmlFormatOptions {
+    fn default() -> Self {
+        Self {
+            bar_markers: true,
+            measures_per_line: 4,
+            annotate_time: false,
+        }
+    }
+}
+
+// MML을 마디 단위로 나눠 표시 (공백, "|", /* */ 주석만 넣으므로 strip_mml_formatting으로 원래 문자열이 됨)
+// "MML@a,b,c;" 묶음은 묶음마다 따로, 파트마다 나누고 파트 사이에 빈 줄을 넣음 (묶음 밖의 글자는 그대로)
+pub fn format_mml(text: &str, signatures: &[TimeSignature], options: &MmlFormatOptions) -> Result<String, String> {
+    let stripped = strip_mml_formatting(text);
+    if split_mml_parts(&stripped).is_empty() {
+        return Err("MML 내용이 없습니다".to_string());
+    }
+    if !stripped.contains("MML@") {
+        return format_parts(&[stripped], 0, signatures, options).map(|parts| parts.concat());
+    }
+
+    let mut formatted = String::new();
+    let mut index = 0;
+    for segment in split_mml_blocks(&stripped) {
+        match segment {
+            MmlSegment::Text(other) => formatted.push_str(&other),
+            MmlSegment::Block { parts, closed } => {
+                if !formatted.is_empty() {
+                    formatted.push('\n');
+                }
+                formatted.push_str("MML@\n");
+                formatted.push_str(&format_parts(&parts, index, signatures, options)?.join(",\n\n"));
+                if closed {
+                    formatted.push(';');
+                }
+                index += parts.len();
+            }
+        }
+    }
+    Ok(formatted)
+}
+
+// 한 곡(묶음 하나)의 파트들을 나눔, first_index는 오류 메시지의 파트 번호 기준
+fn format_parts(
+    parts: &[String],
+    first_index: usize,
+    signatures: &[TimeSignature],
+    options: &MmlFormatOptions,
+) -> Result<Vec<String>, String> {
+    let mut parsed = Vec::new();
+    for (index, part) in parts.iter().enumerate() {
+        parsed.push(parse_commands(part).map_err(|e| format!("파트 {}: {}", first_index + index + 1, e))?);
+    }
+
+    // 템포는 어느 파트에 있든 곡 전체에 적용
+    let mut tempos: Vec<(u32, u32)> = parsed.iter().flat_map(|(timeline, _)| timeline.tempos.clone()).collect();
+    tempos.sort_by_key(|&(tick, _)| tick);
+
+    Ok(parts
+        .iter()
+        .zip(parsed.iter())
+        .map(|(part, (_, commands))| format_part(part, commands, signatures, &tempos, options))
+        .collect())
+}
+
+// 표시용 공백, 마디 표시, 주석을 지워 게임에 넣을 문자열로
+pub fn strip_mml_formatting(text: &str) -> String {
+    let mut stripped = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = rest[start + 2..].find("*/").map_or("", |end| &rest[start + 2 + end + 2..]);
+    }
+    stripped.push_str(rest);
+    stripped.retain(|c| !c.is_whitespace() && c != '|');
+    stripped
+}
+
+fn format_part(
+    part: &str,
+    commands: &[(usize, u32)],
+    signatures: &[TimeSignature],
+    tempos: &[(u32, u32)],
+    options: &MmlFormatOptions,
+) -> String {
+    let chars: Vec<char> = part.chars().collect();
+    let end_tick = commands.last().map_or(0, |&(_, tick)| tick);
+    let bars = measure_starts(signatures, end_tick + 1);
+
+    // 각 마디 시작 이후 첫 명령에서 나눔 (마디를 넘는 음 다음의 빈 마디는 건너뜀)
+    let mut measures: Vec<(usize, usize)> = Vec::new(); // (마디 번호, 시작 글자 위치)
+    for &(position, tick) in commands {
+        let measure = bars.partition_point(|&start| start <= tick).saturating_sub(1);
+        if measures.last().is_none_or(|&(last, _)| measure > last) {
+            measures.push((measure, position));
+        }
+    }
+    if measures.is_empty() {
+        return part.to_string();
+    }
+    measures[0].1 = 0;
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut line: Vec<String> = Vec::new();
+    for (i, &(measure, start)) in measures.iter().enumerate() {
+        let end = measures.get(i + 1).map_or(chars.len(), |&(_, next)| next);
+        let body: String = chars[start..end].iter().collect();
+        let text = if options.annotate_time {
+            format!("/* {} {} */ {}", measure + 1, format_time(bars[measure], tempos), body)
+        } else {
+            body
+        };
+        line.push(text);
+
+        if options.measures_per_line > 0 && line.len() >= options.measures_per_line {
+            lines.push(join_measures(&line, options));
+            line.clear();
+        }
+    }
+    if !line.is_empty() {
+        lines.push(join_measures(&line, options));
+    }
+
+    let separator = match (options.bar_markers, options.measures_per_line > 0) {
+        (true, true) => " |\n",
+        (true, false) => " | ",
+        (false, true) => "\n",
+        (false, false) => " ",
+    };
+    lines.join(separator)
+}
+
+fn join_measures(measures: &[String], options: &MmlFormatOptions) -> String {
+    measures.join(if options.bar_markers { " | " } else { " " })
+}
+
+// tick 위치의 재생 시각 (분:초.00), 첫 템포 전에는 기본 템포 120
+fn format_time(tick: u32, tempos: &[(u32, u32)]) -> String {
+    let mut seconds = 0.0;
+    let mut position = 0;
+    let mut bpm = 120;
+    for &(change, tempo) in tempos.iter().take_while(|&&(change, _)| change < tick) {
+        seconds += (change - position) as f64 * 60.0 / (bpm.max(1) as f64 * TPB as f64);
+        position = change;
+        bpm = tempo;
+    }
+    seconds += (tick - position) as f64 * 60.0 / (bpm.max(1) as f64 * TPB as f64);
+    format!("{}:{:05.2}", (seconds / 60.0) as u32, seconds % 60.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PART: &str = "T120L8O4CDEFGAB>C4<B4A4G2R4.L16CDEFGFEDC2&C8.E8T90G1";
+
+    fn all_options() -> Vec<MmlFormatOptions> {
+        let mut options = Vec::new();
+        for bar_markers in [false, true] {
+            for measures_per_line in [0, 1, 4] {
+                for annotate_time in [false, true] {
+                    options.push(MmlFormatOptions {
+                        bar_markers,
+                        measures_per_line,
+                        annotate_time,
+                    });
+                }
+            }
+        }
+        options
+    }
+
+    fn assert_round_trip(text: &str) {
+        let signatures = [TimeSignature::common_time(0), TimeSignature { tick: 1536 * 2, numerator: 3, denominator: 4 }];
+        for options in all_options() {
+            let formatted = format_mml(text, &signatures, &options).unwrap();
+            assert_eq!(strip_mml_formatting(&formatted), text, "{:?}\n{}", options, formatted);
+        }
+    }
+
+    #[test]
+    fn round_trips_single_part() {
+        assert_round_trip(PART);
+    }
+
+    #[test]
+    fn round_trips_mml_block() {
+        assert_round_trip(&format!("MML@{0},{0},;", PART));
+    }
+
+    #[test]
+    fn round_trips_several_blocks_and_surrounding_text() {
+        assert_round_trip(&format!("title:MML@{0},{0};MML@{0};end", PART));
+        assert_round_trip(&format!("MML@{0};MML@{0}", PART));
+    }
+
+    #[test]
+    fn splits_at_measures() {
+        let options = MmlFormatOptions {
+            bar_markers: true,
+            measures_per_line: 0,
+            annotate_time: false,
+        };
+        let formatted = format_mml("L4CDEFGABR", &[TimeSignature::common_time(0)], &options).unwrap();
+        assert_eq!(formatted, "L4CDEF | GABR");
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::converter::{Note, GRID_SIZE, TPB};
+
+pub const TRIPLET_GRID: u32 = 64; // 셋잇단 16분음표
+
+// 퀀타이즈 격자
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantizeGrid {
+    Sixteenth,
+    ThirtySecond,
+    SixtyFourth,
+    // 64분 격자, 박마다 셋잇단 16분이 더 잘 맞으면 셋잇단 (기존 방식)
+    #[default]
+    SixtyFourthWithTriplets,
+    TripletEighth,
+    TripletSixteenth,
+    // 박마다 시작 시점 분포로 격자 추정 (스윙 감지 포함)
+    Auto,
+}
+
+impl QuantizeGrid {
+    // (기본 격자, 박마다 비교할 셋잇단 격자). 자동이면 None
+    fn fixed_grids(self) -> Option<(u32, Option<u32>)> {
+        match self {
+            QuantizeGrid::Sixteenth => Some((96, None)),
+            QuantizeGrid::ThirtySecond => Some((48, None)),
+            QuantizeGrid::SixtyFourth => Some((GRID_SIZE, None)),
+            QuantizeGrid::SixtyFourthWithTriplets => Some((GRID_SIZE, Some(TRIPLET_GRID))),
+            QuantizeGrid::TripletEighth => Some((128, None)),
+            QuantizeGrid::TripletSixteenth => Some((TRIPLET_GRID, None)),
+            QuantizeGrid::Auto => None,
+        }
+    }
+}
+
+// 자동 격자 후보: 16분, 셋잇단 8분, 32분, 셋잇단 16분, 64분 순
+// 대체로 거친 것부터지만, 정박에만 음이 있는 박이 셋잇단으로 잡히지 않도록 2진 격자를 한 단계 거친 셋잇단보다 먼저 시도
+const AUTO_GRIDS: &[u32] = &[96, 128, 48, TRIPLET_GRID, GRID_SIZE];
+
+// 자동 격자에서 허용하는 시작 시점당 평균 오차
+const AUTO_GRID_TOLERANCE: u32 = GRID_SIZE / 2;
+
+// 스윙 8분 뒷박 위치 (박의 2/3 지점)와 허용 오차
+const SWING_OFFBEAT: u32 = TPB * 2 / 3;
+const SWING_TOLERANCE: u32 = TPB / 12;
+
+fn snap_to_grid(tick: u32, grid: u32) -> u32 {
+    ((tick as f32 / grid as f32).round() as u32) * grid
+}
+
+fn snap_error(onsets: &[u32], grid: u32) -> u32 {
+    onsets.iter().map(|&t| t.abs_diff(snap_to_grid(t, grid))).sum()
+}
+
+// 셋잇단 격자 오차가 더 작으면 셋잇단, 아니면 기본 격자
+fn choose_beat_grid(onsets: &[u32], base: u32, triplet: Option<u32>) -> u32 {
+    match triplet {
+        Some(triplet) if snap_error(onsets, triplet) < snap_error(onsets, base) => triplet,
+        _ => base,
+    }
+}
+
+fn is_swing_position(tick: u32) -> bool {
+    let position = tick % TPB;
+    position <= SWING_TOLERANCE
+        || position >= TPB - SWING_TOLERANCE
+        || position.abs_diff(SWING_OFFBEAT) <= SWING_TOLERANCE
+}
+
+// 뒷박 8분음이 박의 절반보다 2/3 지점 근처에 주로 오면 스윙으로 판단
+fn detect_swing(notes: &[Note]) -> bool {
+    let mut swung = 0;
+    let mut straight = 0;
+
+    for note in notes {
+        let position = note.start % TPB;
+        if position.abs_diff(SWING_OFFBEAT) <= SWING_TOLERANCE {
+            swung += 1;
+        } else if position.abs_diff(TPB / 2) <= GRID_SIZE {
+            straight += 1;
+        }
+    }
+
+    swung >= 4 && swung > straight * 2
+}
+
+// 평균 오차가 허용치 이내인 가장 거친 격자 선택
+// 스윙 곡에서 정박/스윙 뒷박만 있는 박은 셋잇단 8분 격자로 유지
+fn choose_auto_grid(onsets: &[u32], swing: bool) -> u32 {
+    if swing && onsets.iter().all(|&t| is_swing_position(t)) {
+        return 128;
+    }
+
+    AUTO_GRIDS
+        .iter()
+        .copied()
+        .find(|&grid| snap_error(onsets, grid) <= AUTO_GRID_TOLERANCE * onsets.len() as u32)
+        .unwrap_or(GRID_SIZE)
+}
+
+// 박 단위로 격자를 정해 시작/끝 시점을 스냅
+pub fn quantize_notes(notes: &mut [Note], grid: QuantizeGrid) {
+    let mut beat_onsets: HashMap<u32, Vec<u32>> = HashMap::new();
+    for note in notes.iter() {
+        beat_onsets.entry(note.start / TPB).or_default().push(note.start);
+    }
+
+    let (default_grid, beat_grids): (u32, HashMap<u32, u32>) = match grid.fixed_grids() {
+        Some((base, triplet)) => (
+            base,
+            beat_onsets
+                .iter()
+                .map(|(&beat, onsets)| (beat, choose_beat_grid(onsets, base, triplet)))
+                .collect(),
+        ),
+        None => {
+            let swing = detect_swing(notes);
+            (
+                GRID_SIZE,
+                beat_onsets
+                    .iter()
+                    .map(|(&beat, onsets)| (beat, choose_auto_grid(onsets, swing)))
+                    .collect(),
+            )
+        }
+    };
+
+    let grid_at = |tick: u32| beat_grids.get(&(tick / TPB)).copied().unwrap_or(default_grid);
+
+    for note in notes.iter_mut() {
+        let grid = grid_at(note.start);
+        let start_snapped = snap_to_grid(note.start, grid);
+        let end_snapped = snap_to_grid(note.end, grid_at(note.end));
+        let mut duration_snapped = end_snapped.saturating_sub(start_snapped);
+
+        if duration_snapped < grid {
+            duration_snapped = grid;
+        }
+
+        note.start = start_snapped;
+        note.end = start_snapped + duration_snapped;
+        note.duration = duration_snapped;
+    }
+}
+
+// 스트럼/롤 정리: tolerance 틱 이내에 연달아 시작하고 서로 겹쳐 울리는 음들을
+// 첫 음의 시작 시점으로 맞춰 하나의 화음으로 만듦 (끝 시점은 유지)
+// quantize_notes 전의 시작 시점에 적용해야 tolerance가 격자와 무관한 실제 간격이 됨
+pub fn collapse_strums(mut notes: Vec<Note>, tolerance: u32) -> Vec<Note> {
+    if tolerance == 0 || notes.is_empty() {
+        return notes;
+    }
+
+    notes.sort_by_key(|n| n.start);
+
+    let mut cluster_start = notes[0].start;
+    let mut cluster: Vec<usize> = Vec::new();
+
+    for i in 0..notes.len() {
+        let start = notes[i].start;
+        let joins = !cluster.is_empty()
+            && start - cluster_start <= tolerance
+            && cluster.iter().all(|&j| notes[j].end > start && notes[j].note != notes[i].note);
+
+        if !joins {
+            cluster_start = start;
+            cluster.clear();
+        }
+        cluster.push(i);
+
+        let note = &mut notes[i];
+        note.start = cluster_start;
+        note.duration = note.end - note.start;
+    }
+
+    notes
+}
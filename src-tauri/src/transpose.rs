@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use crate::converter::Note;
+
+// 조옮김 설정: 반음 수 지정 또는 자동
+// 곡 전체 자동은 반음 단위, 파트별 자동은 옥타브 단위로 이동
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transpose {
+    Semitones(i32),
+    Auto,
+}
+
+impl Default for Transpose {
+    fn default() -> Self {
+        Transpose::Semitones(0)
+    }
+}
+
+// 자동 조옮김에서 시도하는 범위 (반음)
+const AUTO_TRANSPOSE_RANGE: i32 = 12;
+
+// 모든 음을 semitones만큼 이동. MIDI 범위(0~127)를 벗어나면 옥타브 단위로 되돌림
+pub fn transpose_notes(notes: Vec<Note>, semitones: i32) -> Vec<Note> {
+    if semitones == 0 {
+        return notes;
+    }
+
+    notes
+        .into_iter()
+        .map(|mut note| {
+            let mut pitch = note.note as i32 + semitones;
+            while pitch < 0 {
+                pitch += 12;
+            }
+            while pitch > 127 {
+                pitch -= 12;
+            }
+            note.note = pitch as u8;
+            note
+        })
+        .collect()
+}
+
+// generate_mml_final이 출력할 옥타브 명령 수 (연속한 음의 옥타브가 바뀔 때마다 하나)
+pub fn count_octave_changes(voice: &[Note], semitones: i32) -> usize {
+    voice
+        .windows(2)
+        .filter(|pair| {
+            let a = (pair[0].note as i32 + semitones).div_euclid(12);
+            let b = (pair[1].note as i32 + semitones).div_euclid(12);
+            a != b
+        })
+        .count()
+}
+
+// 옥타브 범위 안에 들어오는 음 수
+fn count_in_range(voices: &[Vec<Note>], semitones: i32, octave_range: (i32, i32)) -> usize {
+    let (min_octave, max_octave) = octave_range;
+    voices
+        .iter()
+        .flatten()
+        .filter(|n| {
+            let octave = (n.note as i32 + semitones).div_euclid(12) - 1;
+            octave >= min_octave && octave <= max_octave
+        })
+        .count()
+}
+
+// 후보 중 음역 안의 음이 가장 많고, 옥타브 명령이 가장 적고, 이동량이 가장 작은 값 선택
+fn best_transposition(
+    voices: &[Vec<Note>],
+    octave_range: (i32, i32),
+    candidates: impl Iterator<Item = i32>,
+) -> i32 {
+    candidates
+        .min_by_key(|&shift| {
+            let in_range = count_in_range(voices, shift, octave_range);
+            let octave_changes: usize = voices.iter().map(|v| count_octave_changes(v, shift)).sum();
+            (usize::MAX - in_range, octave_changes, shift.abs())
+        })
+        .unwrap_or(0)
+}
+
+// 곡 전체 자동 조옮김 (-12 ~ +12 반음)
+pub fn auto_transpose(voices: &[Vec<Note>], octave_range: (i32, i32)) -> i32 {
+    best_transposition(
+        voices,
+        octave_range,
+        -AUTO_TRANSPOSE_RANGE..=AUTO_TRANSPOSE_RANGE,
+    )
+}
+
+// 파트 하나의 자동 옥타브 이동 (조성을 유지하도록 12반음 단위, -2 ~ +2 옥타브)
+pub fn auto_octave_shift(voice: &[Note], octave_range: (i32, i32)) -> i32 {
+    let voices = [voice.to_vec()];
+    best_transposition(&voices, octave_range, (-2..=2).map(|o| o * 12))
+}
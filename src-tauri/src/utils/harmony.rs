@@ -0,0 +1,64 @@
+// 화성 분석 유틸 (코드 루트 추정 및 구성음 역할)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChordRole {
+    // 중요도 순서 (앞쪽일수록 남김)
+    Root,
+    Third,
+    Seventh,
+    Tension,
+    Fifth,
+}
+
+// 루트 기준 음정 (반음)
+const CHORD_TEMPLATES: &[&[u8]] = &[
+    &[0, 4, 7],      // 장3화음
+    &[0, 3, 7],      // 단3화음
+    &[0, 3, 6],      // 감3화음
+    &[0, 4, 8],      // 증3화음
+    &[0, 5, 7],      // sus4
+    &[0, 4, 7, 10],  // 딸림7
+    &[0, 4, 7, 11],  // 장7
+    &[0, 3, 7, 10],  // 단7
+    &[0, 3, 6, 10],  // 반감7
+    &[0, 3, 6, 9],   // 감7
+];
+
+// 음높이 클래스 집합에서 가장 잘 맞는 코드 루트 추정
+// 동점이면 베이스 음을 루트로 우선
+pub fn detect_chord_root(pitch_classes: &[u8], bass_pc: u8) -> Option<u8> {
+    if pitch_classes.is_empty() {
+        return None;
+    }
+
+    let mut best: Option<(i32, bool, u8)> = None;
+
+    for &root in pitch_classes {
+        for template in CHORD_TEMPLATES {
+            let matched = pitch_classes
+                .iter()
+                .filter(|&&pc| template.contains(&((pc + 12 - root) % 12)))
+                .count() as i32;
+            let missing = template.len() as i32 - matched;
+            let extra = pitch_classes.len() as i32 - matched;
+            let score = matched * 2 - missing - extra;
+
+            let candidate = (score, root == bass_pc, root);
+            if best.is_none_or(|b| (candidate.0, candidate.1) > (b.0, b.1)) {
+                best = Some(candidate);
+            }
+        }
+    }
+
+    best.map(|(_, _, root)| root)
+}
+
+pub fn chord_role(root: u8, pitch_class: u8) -> ChordRole {
+    match (pitch_class + 12 - root) % 12 {
+        0 => ChordRole::Root,
+        3 | 4 => ChordRole::Third,
+        10 | 11 => ChordRole::Seventh,
+        6..=8 => ChordRole::Fifth,
+        _ => ChordRole::Tension,
+    }
+}
@@ -166,4 +166,98 @@ pub fn get_instrument_name(program: u8) -> String {
     };
     
     name.to_string()
-}
\ No newline at end of file
+}
+
+// 악보의 파트 이름으로 GM 프로그램 번호 추정 (대소문자 무시)
+// GM 이름과 정확히 같으면 그 번호, 아니면 흔한 악기 이름이 포함되어 있는지로 판단
+pub fn find_program_by_name(name: &str) -> Option<u8> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return None;
+    }
+
+    if let Some(program) = (0..128u8).find(|&p| get_instrument_name(p).to_lowercase() == name) {
+        return Some(program);
+    }
+
+    // 더 구체적인 이름을 먼저 검사 (bassoon이 bass보다, alto sax가 alto보다 앞)
+    // 다른 키워드를 포함하는 여러 단어 이름은 맨 앞에 (bass guitar ⊃ guitar, contrabassoon ⊃ contrabass)
+    const KEYWORDS: &[(&str, u8)] = &[
+        ("bass guitar", 33),
+        ("electric bass", 33),
+        ("contrabassoon", 70),
+        ("english horn", 69),
+        ("cor anglais", 69),
+        ("electric piano", 4),
+        ("harpsichord", 6),
+        ("piano", 0),
+        ("celesta", 8),
+        ("glockenspiel", 9),
+        ("music box", 10),
+        ("vibraphone", 11),
+        ("marimba", 12),
+        ("xylophone", 13),
+        ("organ", 19),
+        ("accordion", 21),
+        ("harmonica", 22),
+        ("electric guitar", 27),
+        ("guitar", 24),
+        ("lute", 24),
+        ("contrabass", 43),
+        ("double bass", 43),
+        ("violin", 40),
+        ("viola", 41),
+        ("cello", 42),
+        ("harp", 46),
+        ("timpani", 47),
+        ("strings", 48),
+        ("trumpet", 56),
+        ("trombone", 57),
+        ("tuba", 58),
+        ("horn", 60),
+        ("sax", 65),
+        ("oboe", 68),
+        ("bassoon", 70),
+        ("clarinet", 71),
+        ("piccolo", 72),
+        ("flute", 73),
+        ("recorder", 74),
+        ("whistle", 78),
+        ("ocarina", 79),
+        ("banjo", 105),
+        ("bagpipe", 109),
+        ("fiddle", 110),
+        ("bass", 32),
+        ("choir", 52),
+        ("voice", 52),
+        ("soprano", 52),
+        ("alto", 52),
+        ("tenor", 52),
+    ];
+
+    KEYWORDS
+        .iter()
+        .find(|(keyword, _)| name.contains(keyword))
+        .map(|&(_, program)| program)
+}
+
+// GM 프로그램 번호를 MabiIcco 악기 번호로 (가장 비슷한 게임 악기, 없으면 류트)
+// 0 류트, 1 우쿨렐레, 2 만돌린, 3 휘슬, 4 롱카롱, 5 플루트, 6 샬루모, 18 튜바, 19 리라, 20 일렉트릭 기타
+pub fn get_mabiicco_program(program: u8) -> u8 {
+    match program {
+        26..=31 => 20,       // 일렉트릭 기타
+        46 => 19,            // 하프
+        104..=107 => 2,      // 시타르, 밴조 등 발현악기
+        57 | 58 => 18,       // 트롬본, 튜바
+        56 | 59..=71 => 6,   // 금관, 리드
+        72 | 73 => 5,        // 피콜로, 플루트
+        74 | 75 | 79 => 4,   // 리코더, 팬플루트, 오카리나
+        76..=78 => 3,        // 휘슬류
+        _ => 0,
+    }
+}
+
+// 게임에서 연주 가능한 옥타브 범위 (O1 ~ O8)
+// 악기별 음역은 지원하지 않음: 모든 악기에 같은 범위를 쓰고, 좁히려면 변환 옵션의 octave_range를 지정
+pub const MIN_PLAYABLE_OCTAVE: i32 = 1;
+pub const MAX_PLAYABLE_OCTAVE: i32 = 8;
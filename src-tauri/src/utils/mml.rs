@@ -5,4 +5,51 @@ pub fn midi_to_note_name(midi_note: u8) -> (String, i32) {
     let note_index = (midi_note % 12) as usize;
     let name = note_names[note_index].to_string();
     (name, octave)
-}
\ No newline at end of file
+}
+
+// MML 문자열의 모든 음표가 옥타브 범위 안에 있는지 검사
+// O 명령과 < > 상대 옥타브를 추적하며, 범위를 벗어난 첫 음표에서 오류 반환
+pub fn check_mml_octaves(mml: &str, min_octave: i32, max_octave: i32) -> Result<(), String> {
+    let chars: Vec<char> = mml.chars().collect();
+    let mut octave = 4;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i].to_ascii_uppercase();
+        match c {
+            'O' => {
+                let mut j = i + 1;
+                if j < chars.len() && chars[j] == '-' {
+                    j += 1;
+                }
+                while j < chars.len() && chars[j].is_ascii_digit() {
+                    j += 1;
+                }
+                let value: String = chars[i + 1..j].iter().collect();
+                octave = value
+                    .parse()
+                    .map_err(|_| format!("잘못된 옥타브 명령 (위치 {})", i))?;
+                i = j;
+                continue;
+            }
+            '<' => octave -= 1,
+            '>' => octave += 1,
+            'A'..='G' if octave < min_octave || octave > max_octave => {
+                return Err(format!(
+                    "음역을 벗어난 음표: O{} {} (위치 {}, 허용 O{}~O{})",
+                    octave, c, i, min_octave, max_octave
+                ));
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    Ok(())
+}
+
+// 게임이 char_limit과 비교하는 글자 수 (바이트가 아닌 문자 단위, 한글 등도 한 글자)
+// 게임마다 세는 규칙이 다르다는 확인된 자료가 없으므로 모든 글자를 그대로 셈
+pub fn mml_char_count(mml: &str) -> usize {
+    mml.chars().count()
+}
@@ -1,2 +1,3 @@
 pub mod instrument;
-pub mod mml;
\ No newline at end of file
+pub mod mml;
+pub mod harmony;
\ No newline at end of file